@@ -33,6 +33,12 @@ fn build_ui(application: &gtk::Application) {
 
     let config = ConfigBuilder::default()
         .data_source(TestDataGenerator::new())
+        .show_time_axis(true)
+        .show_value_axis(true)
+        .show_gridlines(true)
+        .show_legend(true)
+        .show_crosshair(true)
+        .place_cursors_on_click(true)
         .build()
         .unwrap();
     let mut _g = GraphWithControls::build_ui(config, &window, &gdk_window);
@@ -0,0 +1,50 @@
+use crate::{Color, Time};
+
+/// A labeled event to draw on a graph: either a vertical marker at one
+/// time, or a shaded span between two times.
+///
+/// Annotations are added with `Graph::add_annotation()` or returned
+/// from `DataSource::get_annotations()`, and are discarded with the
+/// data around them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Annotation {
+    /// The time of a marker, or the start of a span.
+    pub t0: Time,
+
+    /// The end of a span, or None for a marker.
+    pub t1: Option<Time>,
+
+    /// The label to draw next to the annotation.
+    pub text: String,
+
+    /// The color to draw the annotation in.
+    pub color: Color,
+}
+
+impl Annotation {
+    /// Create a marker at time `t`.
+    pub fn marker<S: Into<String>>(t: Time, text: S, color: Color) -> Annotation {
+        Annotation {
+            t0: t,
+            t1: None,
+            text: text.into(),
+            color,
+        }
+    }
+
+    /// Create a span from time `t0` to `t1`.
+    pub fn span<S: Into<String>>(t0: Time, t1: Time, text: S, color: Color) -> Annotation {
+        Annotation {
+            t0: t0.min(t1),
+            t1: Some(t0.max(t1)),
+            text: text.into(),
+            color,
+        }
+    }
+
+    /// Return the time the annotation ends: the end of a span, or the
+    /// time of a marker.
+    pub fn end_t(&self) -> Time {
+        self.t1.unwrap_or(self.t0)
+    }
+}
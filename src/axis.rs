@@ -0,0 +1,317 @@
+//! Tick placement and labels for graph axes.
+
+use crate::{Color, Time, Value, WallClock};
+use std::time::UNIX_EPOCH;
+
+/// Length of axis ticks in pixels.
+pub(crate) const AXIS_TICK_LENGTH: f64 = 4.0;
+
+/// How to label the ticks on a time axis.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeAxisLabels {
+    /// Raw `Time` values.
+    Raw,
+
+    /// Time since the first data point was received. If a `WallClock`
+    /// is configured this is shown in seconds, otherwise in raw `Time`
+    /// units.
+    Relative,
+
+    /// Wall-clock time, using the `WallClock` in the `Config`. Falls
+    /// back to `Raw` if no `WallClock` is configured.
+    WallClock,
+}
+
+/// Returns the smallest step from the sequence 1, 2, 5, 10, 20, 50, ...
+/// (extended to fractions below 1) that is >= `min_step`.
+pub(crate) fn nice_step(min_step: f64) -> f64 {
+    if min_step.is_nan() || min_step <= 0.0 {
+        return 1.0;
+    }
+    let pow = 10f64.powf(min_step.log10().floor());
+    for mult in &[1.0, 2.0, 5.0, 10.0] {
+        let step = mult * pow;
+        // Allow a little slack for floating point error in log10.
+        if step >= min_step * (1.0 - 1e-9) {
+            return step;
+        }
+    }
+    10.0 * pow
+}
+
+/// Like `nice_step`, but for a step in seconds: steps from 1 second to
+/// 1 day are chosen to be round numbers of seconds, minutes, or hours.
+pub(crate) fn nice_seconds_step(min_step: f64) -> f64 {
+    const STEPS: &[f64] = &[
+        1.0, 2.0, 5.0, 10.0, 15.0, 30.0,
+        60.0, 120.0, 300.0, 600.0, 900.0, 1800.0,
+        3600.0, 7200.0, 3.0 * 3600.0, 6.0 * 3600.0, 12.0 * 3600.0,
+        86400.0,
+    ];
+    if min_step < 1.0 {
+        return nice_step(min_step);
+    }
+    match STEPS.iter().find(|step| **step >= min_step) {
+        Some(step) => *step,
+        None => 86400.0 * nice_step(min_step / 86400.0),
+    }
+}
+
+/// Returns the multiples of `step` in the range [u0, u1].
+pub(crate) fn ticks(u0: f64, u1: f64, step: f64) -> Vec<f64> {
+    assert!(step > 0.0);
+    let k0 = (u0 / step).ceil() as i64;
+    let k1 = (u1 / step).floor() as i64;
+    (k0..=k1).map(|k| k as f64 * step).collect()
+}
+
+/// Returns the ticks for a value axis showing values from v0 to v1,
+/// with ticks at least `min_step` apart.
+pub(crate) fn value_ticks(v0: Value, v1: Value, min_step: f64) -> Vec<Value> {
+    let step = nice_step(min_step.max(1.0));
+    ticks(v0 as f64, v1 as f64, step).into_iter()
+        .map(|v| v as Value)
+        .collect()
+}
+
+/// Returns the number of decimal places required to label multiples of `step`.
+pub(crate) fn decimals_for_step(step: f64) -> usize {
+    if step >= 1.0 {
+        0
+    } else {
+        (-step.log10().floor()).max(0.0) as usize
+    }
+}
+
+pub(crate) fn set_source_color(c: &cairo::Context, col: Color) {
+    c.set_source_rgb(col.r() as f64 / 255.0,
+                     col.g() as f64 / 255.0,
+                     col.b() as f64 / 255.0);
+}
+
+pub(crate) fn select_axis_font(c: &cairo::Context, family: &str, size: f64) {
+    c.select_font_face(family, cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    c.set_font_size(size);
+}
+
+/// Draw a tick on a vertical axis, from the left of x to x at height y,
+/// with `label` right aligned next to it. The label is kept between
+/// `top` and `bottom`.
+pub(crate) fn draw_left_tick(c: &cairo::Context, x: f64, y: f64, label: &str,
+                             top: f64, bottom: f64) {
+    c.move_to(x - AXIS_TICK_LENGTH, y);
+    c.line_to(x, y);
+    c.stroke();
+
+    let extents = c.text_extents(label);
+    let baseline_y = (y - extents.height / 2.0 - extents.y_bearing)
+        .max(top - extents.y_bearing)
+        .min(bottom);
+    c.move_to(x - AXIS_TICK_LENGTH - 2.0 - extents.width - extents.x_bearing,
+              baseline_y);
+    c.show_text(label);
+}
+
+/// Draw a tick on a horizontal axis, down from y at x, with `label`
+/// centred under it on `baseline_y`. The label is kept between 0 and
+/// `right`.
+pub(crate) fn draw_bottom_tick(c: &cairo::Context, x: f64, y: f64, label: &str,
+                               baseline_y: f64, right: f64) {
+    c.move_to(x, y);
+    c.line_to(x, y + AXIS_TICK_LENGTH);
+    c.stroke();
+
+    let extents = c.text_extents(label);
+    c.move_to((x - extents.x_advance / 2.0).min(right - extents.x_advance).max(0.0),
+              baseline_y);
+    c.show_text(label);
+}
+
+/// Computes tick positions and labels for a time axis.
+pub(crate) struct TimeTicks<'a> {
+    labels: TimeAxisLabels,
+    wall_clock: Option<&'a WallClock>,
+    origin_t: Time,
+    step: f64,
+}
+
+impl<'a> TimeTicks<'a> {
+    /// Choose a tick spacing so that ticks are at least `min_step_t`
+    /// apart, measured in `Time` units.
+    pub(crate) fn new(
+        labels: TimeAxisLabels,
+        wall_clock: Option<&'a WallClock>,
+        origin_t: Time,
+        min_step_t: f64,
+    ) -> TimeTicks<'a> {
+        let mut tt = TimeTicks {
+            labels,
+            wall_clock,
+            origin_t,
+            step: 0.0,
+        };
+        let min_step_t = min_step_t.max(1.0);
+        tt.step = match tt.secs_per_t() {
+            Some(secs_per_t) => nice_seconds_step(min_step_t * secs_per_t),
+            None => nice_step(min_step_t),
+        };
+        tt
+    }
+
+    /// Returns the number of seconds per `Time` unit if labels are in seconds.
+    fn secs_per_t(&self) -> Option<f64> {
+        match (self.labels, self.wall_clock) {
+            (TimeAxisLabels::Raw, _) | (_, None) => None,
+            (_, Some(wc)) => Some(wc.time_unit().as_secs_f64()),
+        }
+    }
+
+    /// Returns the label-space value at `t`. Ticks are placed at
+    /// multiples of the step in label-space.
+    fn label_value(&self, t: Time) -> f64 {
+        match (self.labels, self.wall_clock) {
+            (TimeAxisLabels::Raw, _) | (TimeAxisLabels::WallClock, None) => t as f64,
+            (TimeAxisLabels::Relative, None) => t as f64 - self.origin_t as f64,
+            (TimeAxisLabels::Relative, Some(wc)) =>
+                (t as f64 - self.origin_t as f64) * wc.time_unit().as_secs_f64(),
+            (TimeAxisLabels::WallClock, Some(wc)) => {
+                let secs = match wc.to_system_time(t).map(|st| st.duration_since(UNIX_EPOCH)) {
+                    Some(Ok(d)) => d.as_secs_f64(),
+                    Some(Err(e)) => -e.duration().as_secs_f64(),
+                    // Out of range: fall back to raw values.
+                    None => return t as f64,
+                };
+                secs + wc.utc_offset_secs() as f64
+            },
+        }
+    }
+
+    /// Returns the ticks with times in the range [t0, t1], with their labels.
+    pub(crate) fn ticks(&self, t0: Time, t1: Time) -> Vec<(Time, String)> {
+        if t1 < t0 {
+            return vec![];
+        }
+        let u0 = self.label_value(t0);
+        let u_per_t = self.secs_per_t().unwrap_or(1.0);
+        let u1 = u0 + (t1 - t0) as f64 * u_per_t;
+        ticks(u0, u1, self.step).into_iter()
+            .map(|u| {
+                let t = t0 + ((u - u0) / u_per_t).round().max(0.0) as Time;
+                (t, self.label(t, u))
+            })
+            .collect()
+    }
+
+    fn label(&self, t: Time, u: f64) -> String {
+        match (self.labels, self.wall_clock) {
+            (TimeAxisLabels::Raw, _) | (TimeAxisLabels::WallClock, None) => t.to_string(),
+            (TimeAxisLabels::Relative, None) => format!("{:+}", u),
+            (TimeAxisLabels::Relative, Some(_)) => format_relative_secs(u, self.step),
+            (TimeAxisLabels::WallClock, Some(wc)) => {
+                let fmt = if self.step >= 86400.0 {
+                    "%Y-%m-%d"
+                } else if self.step >= 1.0 {
+                    "%H:%M:%S"
+                } else if self.step >= 1e-3 {
+                    "%H:%M:%S%.3f"
+                } else {
+                    "%M:%S%.6f"
+                };
+                wc.format_with(t, fmt)
+            },
+        }
+    }
+}
+
+/// Format a relative time in seconds, with precision suitable for ticks `step` seconds apart.
+fn format_relative_secs(u: f64, step: f64) -> String {
+    let sign = if u < 0.0 { '-' } else { '+' };
+    let abs = u.abs();
+    if step >= 60.0 {
+        let total = abs.round() as u64;
+        format!("{}{}:{:02}:{:02}", sign, total / 3600, (total / 60) % 60, total % 60)
+    } else {
+        format!("{}{:.*}s", sign, decimals_for_step(step), abs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decimals_for_step, nice_seconds_step, nice_step, ticks,
+                value_ticks, TimeAxisLabels, TimeTicks};
+    use crate::WallClockBuilder;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn nice_steps() {
+        assert_eq!(nice_step(1.0), 1.0);
+        assert_eq!(nice_step(1.5), 2.0);
+        assert_eq!(nice_step(3.0), 5.0);
+        assert_eq!(nice_step(7.0), 10.0);
+        assert_eq!(nice_step(4000.0), 5000.0);
+        assert!((nice_step(0.03) - 0.05).abs() < 1e-12);
+
+        assert_eq!(nice_seconds_step(11.0), 15.0);
+        assert_eq!(nice_seconds_step(45.0), 60.0);
+        assert_eq!(nice_seconds_step(4000.0), 7200.0);
+        assert_eq!(nice_seconds_step(2.0 * 86400.0), 2.0 * 86400.0);
+        assert!((nice_seconds_step(0.3) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tick_values() {
+        assert_eq!(ticks(0.0, 10.0, 5.0), vec![0.0, 5.0, 10.0]);
+        assert_eq!(ticks(1.0, 9.0, 5.0), vec![5.0]);
+        assert_eq!(ticks(-3.0, 3.0, 2.0), vec![-2.0, 0.0, 2.0]);
+        assert_eq!(decimals_for_step(5.0), 0);
+        assert_eq!(decimals_for_step(0.5), 1);
+        assert_eq!(decimals_for_step(0.02), 2);
+    }
+
+    #[test]
+    fn value_tick_values() {
+        assert_eq!(value_ticks(0, 100, 30.0), vec![0, 50, 100]);
+        assert_eq!(value_ticks(0, 65535, 15000.0),
+                   vec![0, 20000, 40000, 60000]);
+        assert_eq!(value_ticks(10, 12, 0.1), vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn raw_ticks() {
+        let tt = TimeTicks::new(TimeAxisLabels::Raw, None, 0, 800.0);
+        assert_eq!(tt.ticks(1500, 3500),
+                   vec![(2000, "2000".to_owned()), (3000, "3000".to_owned())]);
+    }
+
+    #[test]
+    fn relative_ticks() {
+        let tt = TimeTicks::new(TimeAxisLabels::Relative, None, 1000, 800.0);
+        assert_eq!(tt.ticks(1500, 3500),
+                   vec![(2000, "+1000".to_owned()), (3000, "+2000".to_owned())]);
+
+        let wc = WallClockBuilder::default()
+            .time_unit(Duration::from_millis(1))
+            .build()
+            .unwrap();
+        let tt = TimeTicks::new(TimeAxisLabels::Relative, Some(&wc), 1000, 400.0);
+        assert_eq!(tt.ticks(1000, 2100),
+                   vec![(1000, "+0.0s".to_owned()),
+                        (1500, "+0.5s".to_owned()),
+                        (2000, "+1.0s".to_owned())]);
+    }
+
+    #[test]
+    fn wall_clock_ticks() {
+        let wc = WallClockBuilder::default()
+            .epoch(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+            .time_unit(Duration::from_millis(1))
+            .build()
+            .unwrap();
+        // Epoch is 12:26:40 UTC, ticks every 15s should be aligned to the minute.
+        let tt = TimeTicks::new(TimeAxisLabels::WallClock, Some(&wc), 0, 11_000.0);
+        assert_eq!(tt.ticks(0, 40_000),
+                   vec![(5_000, "12:26:45".to_owned()),
+                        (20_000, "12:27:00".to_owned()),
+                        (35_000, "12:27:15".to_owned())]);
+    }
+}
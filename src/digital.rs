@@ -0,0 +1,78 @@
+//! Tracking the runs of equal values of a digital channel, used to
+//! label state bars, see `LaneStyle::States`.
+
+use crate::{Point, Time, Value};
+use std::collections::BTreeMap;
+
+/// The times where one channel's value changes.
+pub(crate) struct Transitions {
+    pub(crate) ch: usize,
+    /// The value of each run, by the time of its first point.
+    runs: BTreeMap<Time, Value>,
+    last: Option<Value>,
+}
+
+impl Transitions {
+    pub(crate) fn new(ch: usize) -> Transitions {
+        Transitions {
+            ch,
+            runs: BTreeMap::new(),
+            last: None,
+        }
+    }
+
+    /// Add points, which must be later than any seen before.
+    pub(crate) fn ingest(&mut self, points: &[Point]) {
+        for p in points {
+            let v = match p.vals().get(self.ch) {
+                Some(v) => *v,
+                None => continue,
+            };
+            if self.last != Some(v) {
+                self.runs.insert(p.t, v);
+                self.last = Some(v);
+            }
+        }
+    }
+
+    /// Forget runs that end before `t`.
+    pub(crate) fn discard_before(&mut self, t: Time) {
+        let first_kept = match self.runs.range(..=t).next_back() {
+            Some((start, _)) => *start,
+            None => return,
+        };
+        self.runs = self.runs.split_off(&first_kept);
+    }
+
+    /// Returns the start time and value of each run that overlaps the
+    /// times t0 up to t1.
+    pub(crate) fn runs(&self, t0: Time, t1: Time) -> Vec<(Time, Value)> {
+        let mut rv: Vec<(Time, Value)> = self.runs.range(..=t0).next_back()
+            .map(|(t, v)| (*t, *v))
+            .into_iter()
+            .collect();
+        rv.extend(self.runs.range(t0..t1)
+                      .filter(|(t, _)| **t != t0)
+                      .map(|(t, v)| (*t, *v)));
+        rv
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Transitions;
+    use crate::test_util::points;
+
+    #[test]
+    fn runs() {
+        let mut tr = Transitions::new(1);
+        tr.ingest(&points(0, 1, &[1, 1, 2, 2]));
+        tr.ingest(&points(40, 1, &[2, 0, 1]));
+        assert_eq!(tr.runs(0, 100), vec![(0, 1), (20, 2), (50, 0), (60, 1)]);
+        assert_eq!(tr.runs(25, 55), vec![(20, 2), (50, 0)]);
+        assert_eq!(tr.runs(50, 51), vec![(50, 0)]);
+
+        tr.discard_before(30);
+        assert_eq!(tr.runs(0, 100), vec![(20, 2), (50, 0), (60, 1)]);
+    }
+}
@@ -0,0 +1,162 @@
+//! Frequency analysis for `Spectrum`: resampling to uniform intervals,
+//! window functions, and a fast Fourier transform.
+
+use crate::Time;
+use std::f64::consts::PI;
+
+/// A window function applied to samples before the FFT, to reduce
+/// spectral leakage.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WindowFunction {
+    /// No window. Best frequency resolution, most leakage.
+    Rectangular,
+
+    /// A good general purpose window.
+    Hann,
+
+    /// Like `Hann`, with lower nearby side lobes but higher distant ones.
+    Hamming,
+
+    /// Low leakage, at the cost of wider peaks.
+    Blackman,
+}
+
+impl WindowFunction {
+    /// Returns the window's coefficient for sample i of n.
+    pub(crate) fn coefficient(self, i: usize, n: usize) -> f64 {
+        if n < 2 {
+            return 1.0;
+        }
+        let x = 2.0 * PI * i as f64 / (n - 1) as f64;
+        match self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => 0.5 - 0.5 * x.cos(),
+            WindowFunction::Hamming => 0.54 - 0.46 * x.cos(),
+            WindowFunction::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+        }
+    }
+}
+
+/// Resample `samples`, which may be at irregular times, to `n` samples
+/// evenly spaced from the first sample's time to the last's, by linear
+/// interpolation.
+///
+/// Returns the new samples and the interval between them in `Time`
+/// units, or None if there are fewer than 2 samples or they all have
+/// the same time.
+pub(crate) fn resample(samples: &[(Time, f64)], n: usize) -> Option<(Vec<f64>, f64)> {
+    let (t_first, t_last) = match (samples.first(), samples.last()) {
+        (Some(first), Some(last)) if last.0 > first.0 && n >= 2 => (first.0, last.0),
+        _ => return None,
+    };
+    let dt = (t_last - t_first) as f64 / (n - 1) as f64;
+    let mut rv = Vec::with_capacity(n);
+    let mut i = 0;
+    for k in 0..n {
+        let t = t_first as f64 + k as f64 * dt;
+        while i + 2 < samples.len() && (samples[i + 1].0 as f64) < t {
+            i += 1;
+        }
+        let (t0, v0) = (samples[i].0 as f64, samples[i].1);
+        let (t1, v1) = (samples[i + 1].0 as f64, samples[i + 1].1);
+        let frac = ((t - t0) / (t1 - t0)).clamp(0.0, 1.0);
+        rv.push(v0 + (v1 - v0) * frac);
+    }
+    Some((rv, dt))
+}
+
+/// An in-place radix-2 FFT. The length of `re` and `im` must be the
+/// same power of 2.
+pub(crate) fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    assert_eq!(n, im.len());
+    assert!(n.is_power_of_two());
+
+    // Bit-reversal permutation.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Returns the amplitude of each frequency bin from 0 up to half the
+/// sample rate, for `samples` with `window` applied. The length of
+/// `samples` must be a power of 2.
+///
+/// A sine wave of amplitude A shows as a peak of about A.
+pub(crate) fn amplitude_spectrum(samples: &[f64], window: WindowFunction) -> Vec<f64> {
+    let n = samples.len();
+    let coefficients: Vec<f64> = (0..n).map(|i| window.coefficient(i, n)).collect();
+    let gain: f64 = coefficients.iter().sum();
+    let mut re: Vec<f64> = samples.iter().zip(coefficients.iter())
+        .map(|(s, c)| s * c)
+        .collect();
+    let mut im = vec![0.0; n];
+    fft(&mut re, &mut im);
+    (0..=n / 2)
+        .map(|k| {
+            let scale = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+            scale * (re[k] * re[k] + im[k] * im[k]).sqrt() / gain
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{amplitude_spectrum, resample, WindowFunction};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn resample_irregular() {
+        let samples = vec![(0, 0.0), (1, 10.0), (4, 40.0), (5, 20.0)];
+        let (rv, dt) = resample(&samples, 6).unwrap();
+        assert_eq!(dt, 1.0);
+        assert_eq!(rv, vec![0.0, 10.0, 20.0, 30.0, 40.0, 20.0]);
+        assert!(resample(&samples[..1], 6).is_none());
+    }
+
+    #[test]
+    fn sine_peak() {
+        let n = 256;
+        // 16 cycles in the window, amplitude 3, plus an offset of 1.
+        let samples: Vec<f64> = (0..n)
+            .map(|i| 1.0 + 3.0 * (2.0 * PI * 16.0 * i as f64 / n as f64).sin())
+            .collect();
+        let amps = amplitude_spectrum(&samples, WindowFunction::Rectangular);
+        assert_eq!(amps.len(), n / 2 + 1);
+        assert!((amps[0] - 1.0).abs() < 1e-9);
+        assert!((amps[16] - 3.0).abs() < 1e-9);
+        assert!(amps[20] < 1e-9);
+
+        let amps = amplitude_spectrum(&samples, WindowFunction::Hann);
+        let peak = (0..amps.len()).max_by(|a, b| amps[*a].partial_cmp(&amps[*b]).unwrap());
+        assert_eq!(peak, Some(16));
+    }
+}
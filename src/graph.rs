@@ -1,16 +1,79 @@
-use crate::{Color, DataSource, observable_value, Point, Result, Store, Time, Value};
+use crate::{
+    Annotation, Band, Crossing, Lane, LaneStyle, Threshold,
+    axis::{AXIS_TICK_LENGTH, draw_left_tick, select_axis_font, set_source_color,
+           TimeAxisLabels, TimeTicks, value_ticks},
+    digital::Transitions,
+    lane::{check_lanes, lane_rect},
+    Phosphor, phosphor::Intensity,
+    Spectrogram,
+    pixel_map::{pixel_index, pixel_to_t, snap_t, t_to_pixel},
+    threshold::{detect_crossings, highlight_color},
+    Trigger, trigger::TriggerState,
+    Color, DataSource, Error, observable_value, Point, Result, Signal, Store, Time, Value,
+    WallClock,
+};
 use gdk::prelude::*;
 use glib::source::Continue;
 use gtk::prelude::*;
 use std::{
     cell::{Cell, RefCell, RefMut},
     rc::Rc,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 const BYTES_PER_PIXEL: usize = 4;
 const BACKGROUND_COLOR: (f64, f64, f64) = (0.4, 0.4, 0.4);
 const DRAWN_AREA_BACKGROUND_COLOR: (f64, f64, f64) = (0.0, 0.0, 0.0);
+const TIME_AXIS_HEIGHT: u32 = 20;
+/// Minimum distance between ticks on the time axis in pixels, which
+/// leaves room for their labels.
+const TIME_AXIS_MIN_TICK_SPACING: f64 = 120.0;
+const VALUE_AXIS_WIDTH: u32 = 50;
+/// Minimum distance between ticks on the value axis in pixels.
+const VALUE_AXIS_MIN_TICK_SPACING: f64 = 30.0;
+const LEGEND_BACKGROUND_COLOR: (f64, f64, f64, f64) = (0.0, 0.0, 0.0, 0.6);
+/// Distance between the legend and the edges of the graph in pixels.
+const LEGEND_MARGIN: f64 = 8.0;
+/// Padding around and between items in the legend in pixels.
+const LEGEND_PADDING: f64 = 6.0;
+/// Opacity of the label of a hidden channel in the legend.
+const LEGEND_HIDDEN_ALPHA: f64 = 0.4;
+/// Distance in pixels the pointer must move with a button held for it to
+/// count as a drag rather than a click.
+const DRAG_THRESHOLD: f64 = 3.0;
+/// The factor the zoom changes by for each step of the mouse wheel.
+const WHEEL_ZOOM_FACTOR: f64 = 1.25;
+/// Radius of the circles marking the hovered point on each channel.
+const CROSSHAIR_MARKER_RADIUS: f64 = 3.0;
+/// Distance in pixels from the pointer to the tooltip.
+const TOOLTIP_OFFSET: f64 = 12.0;
+/// The tooltip only shows a point if it is within this many pixels of
+/// the pointer.
+const HOVER_MAX_DISTANCE: f64 = 10.0;
+/// Colors of measurement cursors A and B.
+const CURSOR_COLORS: [(f64, f64, f64); 2] = [(1.0, 0.85, 0.0), (0.0, 0.85, 1.0)];
+/// Opacity of the shading of a `Band`.
+const BAND_ALPHA: f64 = 0.2;
+/// Opacity of the shading of an annotation span.
+const ANNOTATION_SPAN_ALPHA: f64 = 0.2;
+/// Annotation labels are staggered over this many rows, so labels of
+/// annotations close together overlap less.
+const ANNOTATION_LABEL_ROWS: usize = 3;
+/// Color of the shaded time-range selection, as RGBA.
+const SELECTION_COLOR: (f64, f64, f64, f64) = (0.3, 0.5, 1.0, 0.25);
+/// Fill colors of state bars, see `LaneStyle::States`, chosen by value.
+const STATE_COLORS: [Color; 6] = [
+    Color(40, 60, 110), Color(30, 90, 60), Color(110, 70, 30),
+    Color(90, 40, 100), Color(30, 90, 100), Color(100, 40, 50),
+];
+/// Color of the box drawn while box zooming, as RGBA.
+const ZOOM_BOX_COLOR: (f64, f64, f64, f64) = (1.0, 1.0, 1.0, 0.2);
+/// Color of the outline of the box drawn while box zooming, as RGBA.
+const ZOOM_BOX_OUTLINE_COLOR: (f64, f64, f64, f64) = (1.0, 1.0, 1.0, 0.8);
+
+/// Draws a single data point at x, y by calling the plot function
+/// with each pixel it covers. Pixels may be outside the patch buffer.
+type PointFunc = dyn Fn(usize, usize, &mut dyn FnMut(usize, usize));
 
 struct State {
     backing_surface: RefCell<cairo::Surface>,
@@ -18,6 +81,60 @@ struct State {
 
     store: RefCell<Store>,
 
+    /// The name of each channel, i.e. each value in a `Point`.
+    channel_names: Vec<String>,
+    /// The units of each channel, empty if it has none.
+    channel_units: Vec<String>,
+    /// The lanes channels are drawn in. If empty, all channels are
+    /// drawn over the whole graph.
+    lanes: RefCell<Vec<Lane>>,
+    /// The runs of each channel drawn as state bars, to label them.
+    transitions: RefCell<Vec<Transitions>>,
+
+    channels_write: RefCell<observable_value::WriteHalf<Channels>>,
+    channels_read: RefCell<observable_value::ReadHalf<Channels>>,
+
+    thresholds: RefCell<Vec<Threshold>>,
+    bands: RefCell<Vec<Band>>,
+    /// Whether the latest value was beyond each threshold.
+    beyond_thresholds: RefCell<Vec<bool>>,
+    /// Raised when a value crosses a threshold.
+    crossing_signal: RefCell<Signal<Crossing>>,
+
+    /// The trigger for `ViewMode::Triggered`, if one has been set.
+    trigger: RefCell<Option<TriggerState>>,
+
+    phosphor: RefCell<Option<Phosphor>>,
+    /// If set, the whole graph is drawn as a spectrogram when there
+    /// are no lanes.
+    spectrogram: RefCell<Option<Spectrogram>>,
+    /// Intensity accumulated over sweeps in `ViewMode::Triggered`.
+    persistence: RefCell<Option<Persistence>>,
+
+    cursors_read: RefCell<observable_value::ReadHalf<Cursors>>,
+    cursors_write: RefCell<observable_value::WriteHalf<Cursors>>,
+
+    /// Where each legend entry was last drawn, to handle clicks.
+    legend_entries: RefCell<Vec<LegendEntry>>,
+
+    /// What dragging with the left mouse button does.
+    drag_action: Cell<DragAction>,
+    /// The drag in progress, if any.
+    drag: RefCell<Option<Drag>>,
+    /// The position of the pointer on `drawing_area`, if it is over it.
+    hover: Cell<Option<(f64, f64)>>,
+    /// Raised when the graph is clicked with the left mouse button.
+    click_signal: RefCell<Signal<(f64, f64)>>,
+    /// The selected time range, if any.
+    selection_read: RefCell<observable_value::ReadHalf<Option<(Time, Time)>>>,
+    selection_write: RefCell<observable_value::WriteHalf<Option<(Time, Time)>>>,
+    /// Raised when the selected time range changes.
+    selection_signal: RefCell<Signal<(Time, Time)>>,
+    /// Views to return to with `Graph::zoom_back()`, most recent last.
+    zoom_history: RefCell<Vec<View>>,
+    /// The zoom animation in progress, if any.
+    zoom_animation: RefCell<Option<ZoomAnimation>>,
+
     drawing_area: gtk::DrawingArea,
 
     view_write: RefCell<observable_value::WriteHalf<View>>,
@@ -29,6 +146,242 @@ struct State {
     config: Config,
 
     tick_id: RefCell<TickId>,
+
+    /// The current graph size in pixels, not including axes.
+    graph_width: Cell<u32>,
+    graph_height: Cell<u32>,
+
+    /// Used to create surfaces when the graph is resized.
+    gdk_window: gdk::Window,
+
+    /// The number of device pixels per logical pixel, e.g. 2 on HiDPI
+    /// displays. Surfaces and patches are rendered at device resolution.
+    scale_factor: Cell<i32>,
+}
+
+impl State {
+    fn graph_width(&self) -> u32 {
+        self.graph_width.get()
+    }
+
+    fn graph_height(&self) -> u32 {
+        self.graph_height.get()
+    }
+
+    /// Height of the drawing area in pixels: the graph plus any axis under it.
+    fn drawing_area_height(&self) -> u32 {
+        self.graph_height() + self.config.time_axis_height()
+    }
+
+    fn format_time(&self, t: Time) -> String {
+        match self.config.wall_clock {
+            Some(ref wc) => wc.format(t),
+            None => t.to_string(),
+        }
+    }
+}
+
+/// One of the two measurement cursors, see `Graph::set_cursor()`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Cursor {
+    /// The first cursor. Placed by clicking if `place_cursors_on_click`
+    /// is set in the `Config`.
+    A,
+
+    /// The second cursor. Placed by right clicking if
+    /// `place_cursors_on_click` is set in the `Config`.
+    B,
+}
+
+/// The positions of the measurement cursors and the differences
+/// between them, see `Graph::cursors_observable()`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cursors {
+    /// The time of cursor A, if it is placed.
+    pub a: Option<Time>,
+
+    /// The time of cursor B, if it is placed.
+    pub b: Option<Time>,
+
+    /// The values of the point nearest cursor A, by channel. Empty if
+    /// the cursor is not placed, there is no data, or the data at the
+    /// cursor has been discarded.
+    pub a_values: Vec<Value>,
+
+    /// The values of the point nearest cursor B, by channel. Empty if
+    /// the cursor is not placed, there is no data, or the data at the
+    /// cursor has been discarded.
+    pub b_values: Vec<Value>,
+
+    /// The time from cursor A to cursor B, if both are placed.
+    pub delta_t: Option<i64>,
+
+    /// `delta_t` in seconds, if both cursors are placed and a
+    /// `WallClock` is configured.
+    pub delta_secs: Option<f64>,
+
+    /// 1 / Δt: in Hz if a `WallClock` is configured, otherwise per unit
+    /// of `Time`. None unless both cursors are placed at different times.
+    pub frequency: Option<f64>,
+
+    /// The value at cursor B minus the value at cursor A, by channel.
+    /// Empty unless both cursors have values.
+    pub delta_values: Vec<i32>,
+}
+
+impl Cursors {
+    fn new(store: &Store, wall_clock: Option<&WallClock>,
+           a: Option<Time>, b: Option<Time>) -> Cursors {
+        let first_t = store.first_t();
+        let discarded = |t: Time| first_t > store.origin_t() && t < first_t;
+        let values = |t: Option<Time>| -> Vec<Value> {
+            t.filter(|t| !discarded(*t))
+                .and_then(|t| store.query_nearest(t).unwrap())
+                .map(|pt| pt.vs)
+                .unwrap_or_default()
+        };
+        let (a_values, b_values) = (values(a), values(b));
+        let delta_t = match (a, b) {
+            (Some(a), Some(b)) => Some(b as i64 - a as i64),
+            _ => None,
+        };
+        let delta_secs = match (delta_t, wall_clock) {
+            (Some(dt), Some(wc)) => Some(dt as f64 * wc.time_unit().as_secs_f64()),
+            _ => None,
+        };
+        let frequency = delta_secs.or(delta_t.map(|dt| dt as f64))
+            .filter(|d| *d != 0.0)
+            .map(|d| 1.0 / d.abs());
+        let delta_values = if a_values.is_empty() || b_values.is_empty() {
+            vec![]
+        } else {
+            a_values.iter().zip(b_values.iter())
+                .map(|(va, vb)| *vb as i32 - *va as i32)
+                .collect()
+        };
+        Cursors {
+            a,
+            b,
+            a_values,
+            b_values,
+            delta_t,
+            delta_secs,
+            frequency,
+            delta_values,
+        }
+    }
+
+    /// Returns the cursors at the same times, with values from the
+    /// current contents of `store`.
+    fn refresh(&self, store: &Store, wall_clock: Option<&WallClock>) -> Cursors {
+        Cursors::new(store, wall_clock, self.a, self.b)
+    }
+
+    /// Return the time of a cursor, if it is placed.
+    pub fn get(&self, cursor: Cursor) -> Option<Time> {
+        match cursor {
+            Cursor::A => self.a,
+            Cursor::B => self.b,
+        }
+    }
+}
+
+/// The area a channel's entry in the legend covers on `drawing_area`.
+struct LegendEntry {
+    ch: usize,
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+impl LegendEntry {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.w &&
+            y >= self.y && y < self.y + self.h
+    }
+}
+
+/// A mouse drag in progress on the graph.
+struct Drag {
+    button: u32,
+    action: DragAction,
+    start_x: f64,
+    start_y: f64,
+    start_view: View,
+    /// The latest position of the pointer.
+    x: f64,
+    y: f64,
+    /// Whether the pointer has moved far enough for this to be a drag
+    /// rather than a click.
+    moved: bool,
+}
+
+/// What releasing the mouse button at the end of a `Drag` does.
+#[derive(Debug, Eq, PartialEq)]
+enum DragEnd {
+    /// The pointer didn't move far enough to drag: a click.
+    Click,
+    /// Select the dragged range of time.
+    Select,
+    /// Zoom to the dragged box.
+    BoxZoom,
+    /// Nothing more, e.g. a pan was applied while dragging.
+    Nothing,
+}
+
+impl Drag {
+    fn end(&self) -> DragEnd {
+        match (self.moved, self.action) {
+            (false, _) if self.button == 1 => DragEnd::Click,
+            (true, DragAction::Select) => DragEnd::Select,
+            (true, DragAction::BoxZoom) => DragEnd::BoxZoom,
+            _ => DragEnd::Nothing,
+        }
+    }
+}
+
+/// The phosphor intensity of the sweeps drawn in `ViewMode::Triggered`.
+struct Persistence {
+    /// The patch and view the intensity was drawn with. If any of
+    /// these change, the intensity starts again from zero.
+    key: PersistenceKey,
+    /// The end pixel column of the latest sweep added to `intensity`.
+    px1: i64,
+    intensity: Intensity,
+}
+
+#[derive(PartialEq)]
+struct PersistenceKey {
+    x: usize,
+    w: usize,
+    scale: usize,
+    zoom_x: f64,
+    areas: Vec<PlotArea>,
+}
+
+/// A horizontal strip of the graph that channels are drawn in: the
+/// whole graph, or one `Lane`.
+#[derive(Clone, Debug, PartialEq)]
+struct PlotArea {
+    /// The top of the strip in pixels.
+    y: usize,
+    /// The height of the strip in pixels.
+    h: usize,
+    /// The channels to draw, in the order to draw them.
+    channels: Vec<usize>,
+    value_min: Value,
+    value_max: Value,
+    style: LaneStyle,
+}
+
+/// An animated change of zoom level, see `Graph::animate_zoom_x()`.
+struct ZoomAnimation {
+    from_zoom_x: f64,
+    to_zoom_x: f64,
+    /// The position on `drawing_area` to zoom around, if any.
+    x: Option<f64>,
+    start: Instant,
 }
 
 enum TickId {
@@ -58,6 +411,12 @@ pub struct View {
 
     /// The display mode.
     pub mode: ViewMode,
+
+    /// The value shown at the bottom of the graph.
+    pub value_min: Value,
+
+    /// The value shown at the top of the graph.
+    pub value_max: Value,
 }
 
 /// Describes the display mode of the graph
@@ -68,6 +427,43 @@ pub enum ViewMode {
 
     /// Graph is scrolled to a particular point in time
     Scrolled,
+
+    /// Graph is frozen, see `Graph::pause()`. New data is still
+    /// ingested but not drawn until the graph is resumed.
+    Paused,
+
+    /// Graph draws sweeps aligned to a trigger, like an oscilloscope.
+    /// See `Graph::set_trigger()`.
+    Triggered,
+}
+
+/// Describes how the channels, i.e. the values in each `Point`, are
+/// drawn on the graph.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Channels {
+    /// Whether each channel is drawn, indexed by channel.
+    pub visible: Vec<bool>,
+
+    /// The order to draw the channels in. Channels later in the list
+    /// are drawn on top of earlier ones.
+    pub draw_order: Vec<usize>,
+}
+
+impl Channels {
+    fn new(num_channels: usize) -> Channels {
+        Channels {
+            visible: vec![true; num_channels],
+            draw_order: (0..num_channels).collect(),
+        }
+    }
+
+    /// Returns the channels to draw, in the order to draw them.
+    fn to_draw(&self) -> Vec<usize> {
+        self.draw_order.iter()
+            .copied()
+            .filter(|ch| self.visible[*ch])
+            .collect()
+    }
 }
 
 impl View {
@@ -79,6 +475,8 @@ impl View {
             min_t: 0,
             max_t: 0,
             mode: ViewMode::Following,
+            value_min: c.value_min,
+            value_max: c.value_max,
         }
     }
 }
@@ -98,7 +496,7 @@ impl View {
 ///         .unwrap();
 /// ```
 #[derive(Builder, Debug)]
-#[builder(pattern = "owned")]
+#[builder(pattern = "owned", build_fn(validate = "Self::validate"))]
 pub struct Config {
     /// Maximum zoom out, in units of t per x pixel
     #[builder(default = "1000.0")]
@@ -108,11 +506,13 @@ pub struct Config {
     #[builder(default = "1.0")]
     max_zoom_x: f64,
 
-    /// Graph width in pixels
+    /// Initial graph width in pixels. The graph grows to fill the
+    /// space its container allocates it, but is never narrower than this.
     #[builder(default = "800")]
     graph_width: u32,
 
-    /// Graph height in pixels
+    /// Initial graph height in pixels. The graph grows to fill the
+    /// space its container allocates it, but is never shorter than this.
     #[builder(default = "200")]
     graph_height: u32,
 
@@ -126,6 +526,176 @@ pub struct Config {
     /// The style of point to draw
     #[builder(default = "PointStyle::Point")]
     point_style: PointStyle,
+
+    /// Maps `Time` values to wall-clock time for display. If not set,
+    /// raw `Time` values are displayed.
+    #[builder(default, setter(strip_option))]
+    wall_clock: Option<WallClock>,
+
+    /// Whether to show a time axis under the graph.
+    #[builder(default = "false")]
+    show_time_axis: bool,
+
+    /// How to label the ticks on the time axis.
+    #[builder(default = "TimeAxisLabels::Raw")]
+    time_axis_labels: TimeAxisLabels,
+
+    /// The value initially shown at the bottom of the graph.
+    #[builder(default = "0")]
+    value_min: Value,
+
+    /// The value initially shown at the top of the graph.
+    #[builder(default = "Value::MAX")]
+    value_max: Value,
+
+    /// Whether to show a value axis to the left of the graph.
+    #[builder(default = "false")]
+    show_value_axis: bool,
+
+    /// Whether to draw horizontal gridlines behind the data at each
+    /// value axis tick.
+    #[builder(default = "false")]
+    show_gridlines: bool,
+
+    /// The color of axis ticks and labels.
+    #[builder(default = "Color(230, 230, 230)")]
+    axis_color: Color,
+
+    /// The background color of the axes.
+    #[builder(default = "Color(102, 102, 102)")]
+    axis_background_color: Color,
+
+    /// The color of gridlines.
+    #[builder(default = "Color(64, 64, 64)")]
+    gridline_color: Color,
+
+    /// The font family of axis labels.
+    #[builder(default = "\"Sans\".to_owned()", setter(into))]
+    axis_font_family: String,
+
+    /// The font size of axis labels.
+    #[builder(default = "10.0")]
+    axis_font_size: f64,
+
+    /// Whether to show a legend over the graph, with each channel's
+    /// color, name, and latest value.
+    ///
+    /// Clicking on a channel's legend entry shows or hides that channel.
+    #[builder(default = "false")]
+    show_legend: bool,
+
+    /// Whether the mouse wheel zooms the graph in and out, around the
+    /// time under the pointer.
+    #[builder(default = "true")]
+    mouse_wheel_zoom: bool,
+
+    /// What dragging on the graph with the left mouse button does. This
+    /// can be changed later with `Graph::set_drag_action()`.
+    #[builder(default = "DragAction::Pan")]
+    drag_action: DragAction,
+
+    /// How long zooming with the mouse wheel or `Graph::animate_zoom_x()`
+    /// takes. Zero zooms immediately.
+    #[builder(default = "Duration::from_millis(150)")]
+    zoom_animation_duration: Duration,
+
+    /// Whether to draw a vertical line at the pointer when it is over
+    /// the graph, with a tooltip showing the time and values of the
+    /// nearest point.
+    #[builder(default = "false")]
+    show_crosshair: bool,
+
+    /// Whether clicking on the graph places measurement cursor A, and
+    /// right clicking places cursor B. See `Graph::set_cursor()`.
+    #[builder(default = "false")]
+    place_cursors_on_click: bool,
+
+    /// Limits on channel values, drawn as horizontal lines. Crossings
+    /// are reported by `Graph::crossing_signal()`. These can be changed
+    /// later with `Graph::set_thresholds()`.
+    #[builder(default = "vec![]")]
+    thresholds: Vec<Threshold>,
+
+    /// Ranges of channel values to shade behind the data. These can be
+    /// changed later with `Graph::set_bands()`.
+    #[builder(default = "vec![]")]
+    bands: Vec<Band>,
+
+    /// Horizontal strips to draw groups of channels in, each with its
+    /// own value range. If empty, all channels are drawn over the whole
+    /// graph. These can be changed later with `Graph::set_lanes()`.
+    ///
+    /// `ConfigBuilder::build()` returns an error if a lane is invalid,
+    /// see `Graph::set_lanes()`.
+    #[builder(default = "vec![]")]
+    lanes: Vec<Lane>,
+
+    /// If set, draw points by how often they hit each pixel, like a
+    /// digital phosphor scope. This can be changed later with
+    /// `Graph::set_phosphor()`.
+    #[builder(default, setter(strip_option))]
+    phosphor: Option<Phosphor>,
+
+    /// If set, draw each point as a vertical heatmap strip, with its
+    /// values by channel index from the bottom up and colored by
+    /// magnitude. Lanes can also be drawn this way, see `LaneStyle`.
+    /// This can be changed later with `Graph::set_spectrogram()`.
+    #[builder(default, setter(strip_option))]
+    spectrogram: Option<Spectrogram>,
+
+    /// Whether `GraphWithControls` shows a histogram of each channel's
+    /// values beside the graph, over the visible data or the selection.
+    /// Not used by a plain `Graph`.
+    #[builder(default = "false")]
+    show_histogram: bool,
+}
+
+impl Config {
+    /// Width of the drawing area in pixels: the graph plus any axis to its left.
+    fn drawing_area_width(&self) -> u32 {
+        self.graph_x() + self.graph_width
+    }
+
+    /// Height of the drawing area in pixels: the graph plus any axis under it.
+    fn drawing_area_height(&self) -> u32 {
+        self.graph_height + self.time_axis_height()
+    }
+
+    /// Height of the time axis in pixels, 0 if it is not shown.
+    fn time_axis_height(&self) -> u32 {
+        if self.show_time_axis { TIME_AXIS_HEIGHT } else { 0 }
+    }
+
+    /// The x offset of the graph in the drawing area.
+    fn graph_x(&self) -> u32 {
+        if self.show_value_axis { VALUE_AXIS_WIDTH } else { 0 }
+    }
+
+    pub(crate) fn show_histogram(&self) -> bool {
+        self.show_histogram
+    }
+}
+
+/// What dragging on the graph with the left mouse button does.
+///
+/// Dragging with the middle mouse button always pans.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DragAction {
+    /// Nothing.
+    None,
+
+    /// Pan the graph back and forth in time. This stops the graph
+    /// following the latest data.
+    Pan,
+
+    /// Draw a box over the graph, and zoom to the times and values
+    /// inside it. `Graph::zoom_back()` returns to the previous view.
+    ///
+    /// Dragging with the left mouse button and Alt held always box zooms.
+    BoxZoom,
+
+    /// Select a range of time, see `Graph::selection_signal()`.
+    Select,
 }
 
 /// The style of point to draw
@@ -143,6 +713,17 @@ impl ConfigBuilder {
     pub fn data_source<T: DataSource + 'static>(self, ds: T) -> Self {
         self.data_source_internal(RefCell::new(Box::new(ds)))
     }
+
+    /// Check the lanes draw channels the data source has, as
+    /// `Graph::set_lanes()` does.
+    fn validate(&self) -> std::result::Result<(), String> {
+        if let (Some(lanes), Some(ds)) = (&self.lanes, &self.data_source) {
+            ds.borrow().get_num_values()
+                .and_then(|num_values| check_lanes(lanes, num_values))
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        Ok(())
+    }
 }
 
 /// A GTK widget that draws a graph.
@@ -154,6 +735,15 @@ pub struct Graph {
     s: Rc<State>,
 }
 
+/// Cloning a `Graph` returns another handle to the same widget.
+impl Clone for Graph {
+    fn clone(&self) -> Graph {
+        Graph {
+            s: self.s.clone()
+        }
+    }
+}
+
 impl Graph {
     /// Build and show a `Graph` widget in the target `gtk::Container`.
     pub fn build_ui<C>(config: Config, container: &C, gdk_window: &gdk::Window) -> Graph
@@ -161,27 +751,81 @@ impl Graph {
     {
 
         let drawing_area = gtk::DrawingAreaBuilder::new()
-            .height_request(config.graph_height as i32)
-            .width_request(config.graph_width as i32)
+            .height_request(config.drawing_area_height() as i32)
+            .width_request(config.drawing_area_width() as i32)
+            .hexpand(true)
+            .vexpand(true)
             .build();
         container.add(&drawing_area);
 
         // Initialise State
 
+        let scale_factor = gdk_window.get_scale_factor();
         let backing_surface = create_backing_surface(gdk_window,
-                                                     config.graph_width, config.graph_height);
+                                                     config.graph_width,
+                                                     config.drawing_area_height(),
+                                                     scale_factor);
         let temp_surface = create_backing_surface(gdk_window,
-                                                  config.graph_width, config.graph_height);
-        let store = Store::new(config.data_source.borrow().get_num_values().unwrap() as u8);
+                                                  config.graph_width,
+                                                  config.drawing_area_height(),
+                                                  scale_factor);
+        let num_values = config.data_source.borrow().get_num_values().unwrap();
+        let store = Store::new(num_values as u8);
+        let mut channel_names = config.data_source.borrow().get_names().unwrap();
+        channel_names.truncate(num_values);
+        for ch in channel_names.len()..num_values {
+            channel_names.push(format!("Value {}", ch));
+        }
+        let mut channel_units = config.data_source.borrow().get_units().unwrap();
+        channel_units.resize(num_values, String::new());
         let view = View::default_from_config(&config);
         let (view_read, view_write) =
             observable_value::ObservableValue::new(view.clone()).split();
+        let (channels_read, channels_write) =
+            observable_value::ObservableValue::new(Channels::new(num_values)).split();
+        let (cursors_read, cursors_write) =
+            observable_value::ObservableValue::new(Cursors::default()).split();
+        let (selection_read, selection_write) =
+            observable_value::ObservableValue::new(None).split();
         let s = Rc::new(State {
             backing_surface: RefCell::new(backing_surface),
             temp_surface: RefCell::new(temp_surface),
 
             store: RefCell::new(store),
 
+            channel_names,
+            channel_units,
+            lanes: RefCell::new(config.lanes.clone()),
+            transitions: RefCell::new(lane_transitions(&config.lanes)),
+            channels_read: RefCell::new(channels_read),
+            channels_write: RefCell::new(channels_write),
+
+            thresholds: RefCell::new(config.thresholds.clone()),
+            bands: RefCell::new(config.bands.clone()),
+            beyond_thresholds: RefCell::new(vec![]),
+            crossing_signal: RefCell::new(Signal::new()),
+
+            trigger: RefCell::new(None),
+
+            phosphor: RefCell::new(config.phosphor.clone()),
+            spectrogram: RefCell::new(config.spectrogram.clone()),
+            persistence: RefCell::new(None),
+
+            cursors_read: RefCell::new(cursors_read),
+            cursors_write: RefCell::new(cursors_write),
+
+            legend_entries: RefCell::new(vec![]),
+
+            drag_action: Cell::new(config.drag_action),
+            drag: RefCell::new(None),
+            hover: Cell::new(None),
+            click_signal: RefCell::new(Signal::new()),
+            selection_read: RefCell::new(selection_read),
+            selection_write: RefCell::new(selection_write),
+            selection_signal: RefCell::new(Signal::new()),
+            zoom_history: RefCell::new(vec![]),
+            zoom_animation: RefCell::new(None),
+
             drawing_area: drawing_area.clone(),
 
             view_read: RefCell::new(view_read),
@@ -190,6 +834,12 @@ impl Graph {
             fps_count: Cell::new(0),
             fps_timer: Cell::new(Instant::now()),
 
+            graph_width: Cell::new(config.graph_width),
+            graph_height: Cell::new(config.graph_height),
+
+            gdk_window: gdk_window.clone(),
+            scale_factor: Cell::new(scale_factor),
+
             config,
 
             tick_id: RefCell::new(TickId::None),
@@ -201,7 +851,44 @@ impl Graph {
         // Set signal handlers that require State
         let sc = s.clone();
         drawing_area.connect_draw(move |ctrl, ctx| {
-            graph_draw(ctrl, ctx, &*sc)
+            graph_draw(ctrl, ctx, &sc)
+        });
+
+        let sc = s.clone();
+        drawing_area.connect_size_allocate(move |_ctrl, allocation| {
+            drawing_area_size_allocate(&sc, allocation)
+        });
+
+        let sc = s.clone();
+        drawing_area.connect_property_scale_factor_notify(move |ctrl| {
+            drawing_area_scale_factor_changed(&sc, ctrl.get_scale_factor())
+        });
+
+        drawing_area.add_events(gdk::EventMask::BUTTON_PRESS_MASK |
+                                gdk::EventMask::BUTTON_RELEASE_MASK |
+                                gdk::EventMask::POINTER_MOTION_MASK |
+                                gdk::EventMask::LEAVE_NOTIFY_MASK |
+                                gdk::EventMask::SCROLL_MASK |
+                                gdk::EventMask::SMOOTH_SCROLL_MASK);
+        let gc = graph.clone();
+        drawing_area.connect_button_press_event(move |_ctrl, ev| {
+            drawing_area_button_press(&gc, ev)
+        });
+        let gc = graph.clone();
+        drawing_area.connect_button_release_event(move |_ctrl, ev| {
+            drawing_area_button_release(&gc, ev)
+        });
+        let gc = graph.clone();
+        drawing_area.connect_motion_notify_event(move |_ctrl, ev| {
+            drawing_area_motion_notify(&gc, ev)
+        });
+        let gc = graph.clone();
+        drawing_area.connect_leave_notify_event(move |_ctrl, _ev| {
+            drawing_area_leave_notify(&gc)
+        });
+        let gc = graph.clone();
+        drawing_area.connect_scroll_event(move |_ctrl, ev| {
+            drawing_area_scroll(&gc, ev)
         });
 
         graph.set_frame_tick();
@@ -227,9 +914,10 @@ impl Graph {
             TickId::None => (),
         }
 
-        let sc = self.s.clone();
+        let gc = self.clone();
         let frame_tick_id = self.s.drawing_area.add_tick_callback(move |_ctrl, _clock| {
-            tick(&*sc);
+            step_zoom_animation(&gc);
+            tick(&gc.s);
             Continue(true)
         });
         *self.s.tick_id.borrow_mut() = TickId::EveryFrame(frame_tick_id);
@@ -255,7 +943,7 @@ impl Graph {
             glib::source::timeout_add_seconds_local(
                 1 /* seconds */,
                 move || {
-                    tick(&*sc);
+                    tick(&sc);
                     Continue(true)
                 });
         *self.s.tick_id.borrow_mut() = TickId::IngestOnly(ingest_tick_id);
@@ -273,14 +961,20 @@ impl Graph {
         self.s.drawing_area.hide();
     }
 
-    /// Return the width of the graph
+    /// Return the current width of the graph, not including the value axis.
     pub fn width(&self) -> u32 {
-        self.s.config.graph_width
+        self.s.graph_width()
     }
 
-    /// Return the height of the graph
+    /// Return the current height of the graph, not including the time axis.
     pub fn height(&self) -> u32 {
-        self.s.config.graph_height
+        self.s.graph_height()
+    }
+
+    /// Return the x offset of the graph in `drawing_area`, which is the
+    /// width of the value axis if it is shown.
+    pub fn graph_x_offset(&self) -> u32 {
+        self.s.config.graph_x()
     }
 
     /// Return the initial and maximally zoomed out zoom level, in
@@ -295,6 +989,18 @@ impl Graph {
         self.s.config.max_zoom_x
     }
 
+    /// Return the mapping from `Time` values to wall-clock time, if
+    /// one was configured.
+    pub fn wall_clock(&self) -> Option<&WallClock> {
+        self.s.config.wall_clock.as_ref()
+    }
+
+    /// Format a `Time` value for display: as wall-clock time if a
+    /// `WallClock` was configured, otherwise as the raw value.
+    pub fn format_time(&self, t: Time) -> String {
+        self.s.format_time(t)
+    }
+
     /// Return a description of the current view
     pub fn view(&self) -> View {
         self.s.view_read.borrow().get()
@@ -311,19 +1017,12 @@ impl Graph {
         self.s.store.borrow().first_t()
     }
 
-    fn _clone(&self) -> Graph {
-        Graph {
-            s: self.s.clone()
-        }
-    }
-
     /// Change the zoom level on the graph.
     ///
     /// Any value you pass in will be clamped between `base_zoom_x` and `max_zoom_x`.
     pub fn set_zoom_x(&self, new_zoom_x: f64) {
         debug!("set_zoom_x new_zoom_x={}", new_zoom_x);
-        let new_zoom_x = new_zoom_x.min(self.s.config.base_zoom_x)
-            .max(self.s.config.max_zoom_x);
+        let new_zoom_x = self.clamp_zoom_x(new_zoom_x);
         {
             // Scope the mutable borrow of view.
             let new_view = View {
@@ -333,7 +1032,174 @@ impl Graph {
             self.s.view_write.borrow_mut().set(&new_view);
         }
 
-        redraw_graph(&*self.s);
+        redraw_graph(&self.s);
+    }
+
+    /// Change the zoom level on the graph, keeping the time shown at
+    /// position `x` on `drawing_area` in the same place.
+    ///
+    /// If the graph is following the latest data or triggered, it
+    /// stays that way, and this is the same as `set_zoom_x`.
+    ///
+    /// Any value you pass in will be clamped between `base_zoom_x` and `max_zoom_x`.
+    pub fn set_zoom_x_around(&self, new_zoom_x: f64, x: f64) {
+        debug!("set_zoom_x_around new_zoom_x={} x={}", new_zoom_x, x);
+        let view = self.view();
+        if view.mode == ViewMode::Following || view.mode == ViewMode::Triggered {
+            self.set_zoom_x(new_zoom_x);
+            return;
+        }
+        let new_zoom_x = self.clamp_zoom_x(new_zoom_x);
+        let width = self.s.graph_width() as f64;
+        let x = (x - self.s.config.graph_x() as f64).max(0.0).min(width);
+        let t_x = graph_x_to_t(&view, x);
+        let new_right_t = t_x + (width - x) * new_zoom_x;
+        self.scroll_view(View {
+            zoom_x: new_zoom_x,
+            .. view
+        }, new_right_t - width * new_zoom_x);
+    }
+
+    /// Smoothly change the zoom level on the graph over
+    /// `zoom_animation_duration` from the `Config`. If `x` is `Some`,
+    /// zoom around that position on `drawing_area`, as
+    /// `set_zoom_x_around` does.
+    ///
+    /// If a zoom animation is already running, it continues from the
+    /// current zoom level to the new one.
+    pub fn animate_zoom_x(&self, new_zoom_x: f64, x: Option<f64>) {
+        debug!("animate_zoom_x new_zoom_x={} x={:?}", new_zoom_x, x);
+        let new_zoom_x = self.clamp_zoom_x(new_zoom_x);
+        if self.s.config.zoom_animation_duration == Duration::from_secs(0) {
+            self.s.zoom_animation.replace(None);
+            match x {
+                Some(x) => self.set_zoom_x_around(new_zoom_x, x),
+                None => self.set_zoom_x(new_zoom_x),
+            }
+            return;
+        }
+        self.s.zoom_animation.replace(Some(ZoomAnimation {
+            from_zoom_x: self.view().zoom_x,
+            to_zoom_x: new_zoom_x,
+            x,
+            start: Instant::now(),
+        }));
+    }
+
+    /// Return the zoom level the graph is showing or, if a zoom
+    /// animation is running, the zoom level it will finish at.
+    pub fn target_zoom_x(&self) -> f64 {
+        match self.s.zoom_animation.borrow().as_ref() {
+            Some(a) => a.to_zoom_x,
+            None => self.view().zoom_x,
+        }
+    }
+
+    fn clamp_zoom_x(&self, zoom_x: f64) -> f64 {
+        zoom_x.min(self.s.config.base_zoom_x)
+            .max(self.s.config.max_zoom_x)
+    }
+
+    /// Change what dragging on the graph with the left mouse button does.
+    pub fn set_drag_action(&self, drag_action: DragAction) {
+        self.s.drag_action.set(drag_action);
+    }
+
+    /// Return what dragging on the graph with the left mouse button does.
+    pub fn drag_action(&self) -> DragAction {
+        self.s.drag_action.get()
+    }
+
+    /// Return a signal raised when the graph is clicked with the left
+    /// mouse button, without dragging. The value is the position of the
+    /// click on `drawing_area`.
+    pub fn click_signal(&mut self) -> RefMut<'_, Signal<(f64, f64)>> {
+        self.s.click_signal.borrow_mut()
+    }
+
+    /// Return to the view before the last box zoom.
+    ///
+    /// Returns false if there is no earlier view to return to.
+    pub fn zoom_back(&self) -> bool {
+        debug!("zoom_back");
+        let view = match self.s.zoom_history.borrow_mut().pop() {
+            Some(view) => view,
+            None => return false,
+        };
+        match view.mode {
+            ViewMode::Following => {
+                let view = View {
+                    last_drawn_t: self.s.store.borrow().last_t(),
+                    .. view
+                };
+                self.s.view_write.borrow_mut().set(&view);
+                redraw_graph(&self.s);
+            },
+            ViewMode::Scrolled | ViewMode::Paused => {
+                let left_t = graph_x_to_t(&view, 0.0);
+                self.scroll_view(view, left_t);
+            },
+            ViewMode::Triggered => {
+                self.s.view_write.borrow_mut().set(&view);
+                redraw_graph(&self.s);
+            },
+        }
+        true
+    }
+
+    /// Returns true if `zoom_back()` has an earlier view to return to.
+    pub fn can_zoom_back(&self) -> bool {
+        !self.s.zoom_history.borrow().is_empty()
+    }
+
+    /// Zoom to show times from `t0` to `t1` and, if `values` is
+    /// `Some((value_min, value_max))`, values in that range. The
+    /// current view is saved to return to with `zoom_back()`.
+    ///
+    /// The time zoom is clamped between `base_zoom_x` and `max_zoom_x`,
+    /// keeping the range centred.
+    pub fn zoom_to(&self, t0: Time, t1: Time, values: Option<(Value, Value)>) {
+        debug!("zoom_to t0={} t1={} values={:?}", t0, t1, values);
+        if t1 <= t0 {
+            warn!("zoom_to: t0 must be < t1, ignoring");
+            return;
+        }
+        let view = self.view();
+        let (value_min, value_max) = match values {
+            Some((v0, v1)) if v0 < v1 => (v0, v1),
+            _ => (view.value_min, view.value_max),
+        };
+        self.s.zoom_history.borrow_mut().push(view.clone());
+
+        let width = self.s.graph_width() as f64;
+        let zoom_x = self.clamp_zoom_x((t1 - t0) as f64 / width);
+        let centre_t = (t0 as f64 + t1 as f64) / 2.0;
+        self.scroll_view(View {
+            zoom_x,
+            value_min,
+            value_max,
+            .. view
+        }, (centre_t - width * zoom_x / 2.0).max(0.0));
+    }
+
+    /// Change the range of values shown on the graph, from `value_min`
+    /// at the bottom to `value_max` at the top.
+    pub fn set_value_range(&self, value_min: Value, value_max: Value) {
+        debug!("set_value_range value_min={} value_max={}", value_min, value_max);
+        if value_min >= value_max {
+            warn!("set_value_range: value_min must be < value_max, ignoring");
+            return;
+        }
+        {
+            // Scope the mutable borrow of view.
+            let new_view = View {
+                value_min,
+                value_max,
+                .. self.s.view_read.borrow().get()
+            };
+            self.s.view_write.borrow_mut().set(&new_view);
+        }
+        redraw_graph(&self.s);
     }
 
     /// Sets the graph to follow the latest data.
@@ -348,78 +1214,470 @@ impl Graph {
             };
             self.s.view_write.borrow_mut().set(&new_view);
         }
-        redraw_graph(&*self.s);
+        redraw_graph(&self.s);
+    }
+
+    /// Switch to `ViewMode::Triggered`: wait for `trigger` and then draw
+    /// a sweep aligned to it. `set_follow()` returns to following the
+    /// latest data.
+    ///
+    /// Returns an error if the trigger channel is not a valid channel index.
+    pub fn set_trigger(&self, trigger: Trigger) -> Result<()> {
+        debug!("set_trigger trigger={:?}", trigger);
+        if trigger.channel >= self.s.channel_names.len() {
+            return Err(Error::String(format!("Invalid channel index {}", trigger.channel)));
+        }
+        *self.s.trigger.borrow_mut() = Some(TriggerState::new(trigger));
+        let view = View {
+            mode: ViewMode::Triggered,
+            .. self.view()
+        };
+        self.s.view_write.borrow_mut().set(&view);
+        Ok(())
+    }
+
+    /// Return the trigger set by `set_trigger()`, if any.
+    pub fn trigger(&self) -> Option<Trigger> {
+        self.s.trigger.borrow().as_ref().map(|ts| ts.trigger.clone())
+    }
+
+    /// Wait for another trigger, after a sweep in `TriggerSweep::Single` mode.
+    pub fn rearm_trigger(&self) {
+        debug!("rearm_trigger");
+        if let Some(ts) = self.s.trigger.borrow_mut().as_mut() {
+            ts.rearm();
+        }
+    }
+
+    /// Freeze the graph as it is now. New data is still ingested from
+    /// the `DataSource`, and is shown when the graph is resumed. The
+    /// graph can be scrolled and zoomed while paused.
+    pub fn pause(&self) {
+        debug!("pause");
+        let view = self.view();
+        if view.mode == ViewMode::Paused {
+            return;
+        }
+        self.s.view_write.borrow_mut().set(&View {
+            mode: ViewMode::Paused,
+            .. view
+        });
+    }
+
+    /// Resume drawing new data after `pause()`, jumping to follow the
+    /// latest data.
+    pub fn resume(&self) {
+        debug!("resume");
+        if self.is_paused() {
+            self.set_follow();
+        }
+    }
+
+    /// Returns true if the graph is paused.
+    pub fn is_paused(&self) -> bool {
+        self.view().mode == ViewMode::Paused
     }
 
     /// Scrolls the graph to view a certain time value.
+    ///
+    /// This stops the graph following the latest data. If the graph is
+    /// paused it stays paused.
     pub fn scroll(&self, new_val: f64) {
         debug!("scroll new_val={}", new_val);
-        {
-            // Scope the borrow_mut on view
-            let mut view = self.s.view_read.borrow().get();
+        self.scroll_view(self.view(), new_val);
+    }
+
+    /// Scroll to show `view`, with time `new_val` at the left edge of
+    /// the graph.
+    fn scroll_view(&self, mut view: View, new_val: f64) {
+        let old_view = self.s.view_read.borrow().get();
+        if old_view.mode != ViewMode::Paused {
             view.mode = ViewMode::Scrolled;
-            let new_t = (new_val as u32 +
-                         ((view.zoom_x * self.s.config.graph_width as f64) as u32))
-                .min(self.s.store.borrow().last_t());
-            // Snap new_t to a whole pixel.
-            let new_t = (((new_t as f64) / view.zoom_x).floor() * view.zoom_x) as u32;
-            view.last_drawn_t = new_t;
+        }
+        let new_t = ((new_val.max(0.0) +
+                      view.zoom_x * self.s.graph_width() as f64) as Time)
+            .min(self.s.store.borrow().last_t());
+        // Snap new_t to a whole pixel.
+        view.last_drawn_t = snap_t(new_t, view.zoom_x);
+        debug!("scroll_change, v={:?} view={:?}", new_val, view);
+
+        if scroll_backing_surface(&self.s, &old_view, &view) {
+            view.last_drawn_x = self.s.graph_width();
+            self.s.view_write.borrow_mut().set(&view);
+        } else {
             view.last_drawn_x = 0;
             self.s.view_write.borrow_mut().set(&view);
-            debug!("scroll_change, v={:?} view={:?}", new_val, view);
+            redraw_graph(&self.s);
         }
-        // TODO: Maybe keep the section of the graph that's still valid when scrolling.
-        redraw_graph(&self.s);
     }
 
     /// Return an observable that lets you track the current `View`,
     /// which describes what is currently showing on the graph.
-    pub fn view_observable(&mut self) -> RefMut<observable_value::ReadHalf<View>> {
+    pub fn view_observable(&mut self) -> RefMut<'_, observable_value::ReadHalf<View>> {
         self.s.view_read.borrow_mut()
     }
 
-    /// Returns the `DrawingArea` gtk widget the graph is drawn on, so
-    /// you can connect to its signals.
-    pub fn drawing_area(&self) -> gtk::DrawingArea {
-        self.s.drawing_area.clone()
+    /// Return the points with times from t0 up to t1.
+    pub(crate) fn points_in(&self, t0: Time, t1: Time) -> Vec<Point> {
+        self.s.store.borrow().query_range(t0, t1).unwrap()
     }
 
-    /// Maps a position on `drawing_area` to the data point that is
-    /// currently drawn there. Useful for handling clicks on the graph.
-    ///
-    /// Returns None if no appropriate point can be found, for example
-    /// if the data point for a scroll position has already been
-    /// discarded.
-    pub fn drawing_area_pos_to_point(&self, x: f64, _y: f64) -> Option<Point> {
-        let view = self.s.view_read.borrow().get();
-        let t = (view.last_drawn_t as i64 +
-                 ((x - (view.last_drawn_x as f64)) * view.zoom_x) as i64)
-            .max(0).min(view.last_drawn_t as i64)
-            as u32;
-        let pt = self.s.store.borrow().query_point(t).unwrap()?;
+    /// Return the latest n points ingested, oldest first.
+    pub(crate) fn latest_points(&self, n: usize) -> Vec<Point> {
+        self.s.store.borrow().query_last(n).unwrap()
+    }
 
-        // If we are getting a point >= 10 pixels away, return None instead.
-        // This can happen when old data has been discarded but is still on screen.
-        let pt: Option<Point> = if (pt.t - t) >= (view.zoom_x * 10.0) as u32 {
-            None
-        } else {
-            Some(pt)
-        };
+    /// Return the names of the channels, i.e. the values in each `Point`.
+    pub fn channel_names(&self) -> &[String] {
+        &self.s.channel_names
+    }
 
-        pt
+    /// Return the color each channel is drawn in.
+    pub(crate) fn colors(&self) -> Vec<Color> {
+        self.s.config.data_source.borrow().get_colors().unwrap()
     }
-}
 
-/// Handle the graph's draw signal.
-fn graph_draw(_ctrl: &gtk::DrawingArea, ctx: &cairo::Context, s: &State) -> Inhibit {
+    /// Fill `ctx` with this graph's axis background, and the plot area
+    /// at x, y, w, h with the background of drawn data, so other views
+    /// of the data match the graph.
+    pub(crate) fn fill_background(&self, ctx: &cairo::Context, x: f64, y: f64, w: f64, h: f64) {
+        set_source_color(ctx, self.s.config.axis_background_color);
+        ctx.paint();
+        ctx.set_source_rgb(DRAWN_AREA_BACKGROUND_COLOR.0,
+                           DRAWN_AREA_BACKGROUND_COLOR.1,
+                           DRAWN_AREA_BACKGROUND_COLOR.2);
+        ctx.rectangle(x, y, w, h);
+        ctx.fill();
+    }
+
+    /// Set the source and font of `ctx` to draw axis ticks and labels
+    /// like this graph does.
+    pub(crate) fn set_axis_style(&self, ctx: &cairo::Context) {
+        set_source_color(ctx, self.s.config.axis_color);
+        set_axis_font(ctx, &self.s.config);
+    }
+
+    /// Return the units of the channels, empty for channels without units.
+    pub fn channel_units(&self) -> &[String] {
+        &self.s.channel_units
+    }
+
+    /// Return a description of how the channels are drawn.
+    pub fn channels(&self) -> Channels {
+        self.s.channels_read.borrow().get()
+    }
+
+    /// Show or hide a channel.
+    ///
+    /// Returns an error if `ch` is not a valid channel index.
+    pub fn set_channel_visible(&self, ch: usize, visible: bool) -> Result<()> {
+        debug!("set_channel_visible ch={} visible={}", ch, visible);
+        let mut channels = self.channels();
+        if ch >= channels.visible.len() {
+            return Err(Error::String(format!("Invalid channel index {}", ch)));
+        }
+        channels.visible[ch] = visible;
+        set_channels(&self.s, &channels);
+        Ok(())
+    }
+
+    /// Change the order the channels are drawn in. Channels later in
+    /// `draw_order` are drawn on top of earlier ones.
+    ///
+    /// Returns an error if `draw_order` does not contain each channel
+    /// index exactly once.
+    pub fn set_draw_order(&self, draw_order: &[usize]) -> Result<()> {
+        debug!("set_draw_order draw_order={:?}", draw_order);
+        let mut channels = self.channels();
+        let mut sorted = draw_order.to_vec();
+        sorted.sort_unstable();
+        if sorted != (0..channels.visible.len()).collect::<Vec<usize>>() {
+            return Err(Error::String(format!(
+                "draw_order must contain each channel index once, got {:?}", draw_order)));
+        }
+        channels.draw_order = draw_order.to_vec();
+        set_channels(&self.s, &channels);
+        Ok(())
+    }
+
+    /// Draw a channel on top of all the others.
+    ///
+    /// Returns an error if `ch` is not a valid channel index.
+    pub fn raise_channel(&self, ch: usize) -> Result<()> {
+        let mut draw_order = self.channels().draw_order;
+        if ch >= draw_order.len() {
+            return Err(Error::String(format!("Invalid channel index {}", ch)));
+        }
+        draw_order.retain(|c| *c != ch);
+        draw_order.push(ch);
+        self.set_draw_order(&draw_order)
+    }
+
+    /// Return an observable that lets you track the current `Channels`,
+    /// which describes how the channels are drawn.
+    pub fn channels_observable(&mut self) -> RefMut<'_, observable_value::ReadHalf<Channels>> {
+        self.s.channels_read.borrow_mut()
+    }
+
+    /// Draw channels in stacked lanes, each with its own value range
+    /// and label, sharing the time axis. An empty list draws all
+    /// channels over the whole graph again.
+    ///
+    /// Returns an error if a lane has an invalid channel index or an
+    /// empty value range.
+    pub fn set_lanes(&self, lanes: Vec<Lane>) -> Result<()> {
+        debug!("set_lanes lanes={:?}", lanes);
+        check_lanes(&lanes, self.s.channel_names.len())?;
+        let mut transitions = lane_transitions(&lanes);
+        let points = self.s.store.borrow().query_range(0, Time::MAX).unwrap();
+        for tr in transitions.iter_mut() {
+            tr.ingest(&points);
+        }
+        *self.s.transitions.borrow_mut() = transitions;
+        *self.s.lanes.borrow_mut() = lanes;
+        redraw_graph(&self.s);
+        Ok(())
+    }
+
+    /// Return the lanes channels are drawn in, empty if there are none.
+    pub fn lanes(&self) -> Vec<Lane> {
+        self.s.lanes.borrow().clone()
+    }
+
+    /// Replace the thresholds drawn on the graph and checked for crossings.
+    pub fn set_thresholds(&self, thresholds: Vec<Threshold>) {
+        debug!("set_thresholds thresholds={:?}", thresholds);
+        *self.s.thresholds.borrow_mut() = thresholds;
+        self.s.beyond_thresholds.borrow_mut().clear();
+        redraw_graph(&self.s);
+    }
+
+    /// Return the thresholds drawn on the graph.
+    pub fn thresholds(&self) -> Vec<Threshold> {
+        self.s.thresholds.borrow().clone()
+    }
+
+    /// Replace the bands shaded behind the data.
+    pub fn set_bands(&self, bands: Vec<Band>) {
+        debug!("set_bands bands={:?}", bands);
+        *self.s.bands.borrow_mut() = bands;
+        redraw_graph(&self.s);
+    }
+
+    /// Return the bands shaded behind the data.
+    pub fn bands(&self) -> Vec<Band> {
+        self.s.bands.borrow().clone()
+    }
+
+    /// Turn phosphor drawing on or off, see `ConfigBuilder::phosphor()`.
+    pub fn set_phosphor(&self, phosphor: Option<Phosphor>) {
+        debug!("set_phosphor phosphor={:?}", phosphor);
+        *self.s.phosphor.borrow_mut() = phosphor;
+        *self.s.persistence.borrow_mut() = None;
+        redraw_graph(&self.s);
+    }
+
+    /// Return the phosphor settings, if phosphor drawing is on.
+    pub fn phosphor(&self) -> Option<Phosphor> {
+        self.s.phosphor.borrow().clone()
+    }
+
+    /// Turn spectrogram drawing on or off, see `ConfigBuilder::spectrogram()`.
+    pub fn set_spectrogram(&self, spectrogram: Option<Spectrogram>) {
+        debug!("set_spectrogram spectrogram={:?}", spectrogram);
+        *self.s.spectrogram.borrow_mut() = spectrogram;
+        redraw_graph(&self.s);
+    }
+
+    /// Return the spectrogram settings, if spectrogram drawing is on.
+    pub fn spectrogram(&self) -> Option<Spectrogram> {
+        self.s.spectrogram.borrow().clone()
+    }
+
+    /// Return a signal raised when a value crosses one of the
+    /// thresholds. Data is checked as it is ingested, so this is raised
+    /// even while the graph is hidden.
+    pub fn crossing_signal(&mut self) -> RefMut<'_, Signal<Crossing>> {
+        self.s.crossing_signal.borrow_mut()
+    }
+
+    /// Add an annotation to draw on the graph. It is discarded along
+    /// with the data around it.
+    pub fn add_annotation(&self, a: Annotation) {
+        debug!("add_annotation a={:?}", a);
+        self.s.store.borrow_mut().add_annotation(a);
+        self.s.drawing_area.queue_draw();
+    }
+
+    /// Return the annotations that overlap times t0 to t1 inclusive,
+    /// ordered by start time.
+    pub fn annotations(&self, t0: Time, t1: Time) -> Vec<Annotation> {
+        self.s.store.borrow().query_annotations(t0, t1).into_iter()
+            .map(|(_, a)| a)
+            .collect()
+    }
+
+    /// Remove all annotations.
+    pub fn clear_annotations(&self) {
+        self.s.store.borrow_mut().clear_annotations();
+        self.s.drawing_area.queue_draw();
+    }
+
+    /// Select the time range from `t0` to `t1`, shading it on the
+    /// graph, and raise `selection_signal`.
+    pub fn set_selection(&self, t0: Time, t1: Time) {
+        debug!("set_selection t0={} t1={}", t0, t1);
+        set_selection(&self.s, t0.min(t1), t0.max(t1));
+        self.s.selection_signal.borrow().raise(self.selection().unwrap());
+    }
+
+    /// Remove the time range selection, if any.
+    pub fn clear_selection(&self) {
+        debug!("clear_selection");
+        self.s.selection_write.borrow_mut().set(&None);
+        self.s.drawing_area.queue_draw();
+    }
+
+    /// Return the selected time range, if any.
+    pub fn selection(&self) -> Option<(Time, Time)> {
+        self.s.selection_read.borrow().get()
+    }
+
+    /// Return an observable that lets you track the selected time range,
+    /// including while it is dragged and when it is cleared.
+    pub fn selection_observable(&mut self)
+        -> RefMut<'_, observable_value::ReadHalf<Option<(Time, Time)>>>
+    {
+        self.s.selection_read.borrow_mut()
+    }
+
+    /// Return a signal raised with the selected time range when it
+    /// changes, either when the user finishes dragging a selection
+    /// (see `DragAction::Select`) or from `set_selection`. It is not
+    /// raised by `clear_selection`, see `selection_observable()` for that.
+    pub fn selection_signal(&mut self) -> RefMut<'_, Signal<(Time, Time)>> {
+        self.s.selection_signal.borrow_mut()
+    }
+
+    /// Place a measurement cursor at time `t`, or remove it if `t` is
+    /// None. Cursors stay at the same time as the graph scrolls.
+    pub fn set_cursor(&self, cursor: Cursor, t: Option<Time>) {
+        debug!("set_cursor cursor={:?} t={:?}", cursor, t);
+        set_cursor(&self.s, cursor, t);
+    }
+
+    /// Remove both measurement cursors.
+    pub fn clear_cursors(&self) {
+        self.set_cursor(Cursor::A, None);
+        self.set_cursor(Cursor::B, None);
+    }
+
+    /// Return the positions of the measurement cursors and the
+    /// differences between them.
+    pub fn cursors(&self) -> Cursors {
+        self.s.cursors_read.borrow().get()
+    }
+
+    /// Return an observable that lets you track the measurement cursors.
+    pub fn cursors_observable(&mut self) -> RefMut<'_, observable_value::ReadHalf<Cursors>> {
+        self.s.cursors_read.borrow_mut()
+    }
+
+    /// Returns the `DrawingArea` gtk widget the graph is drawn on, so
+    /// you can connect to its signals.
+    pub fn drawing_area(&self) -> gtk::DrawingArea {
+        self.s.drawing_area.clone()
+    }
+
+    /// Maps a position on `drawing_area` to the data point that is
+    /// currently drawn there. Useful for handling clicks on the graph.
+    ///
+    /// Returns None if no appropriate point can be found, for example
+    /// if the data point for a scroll position has already been
+    /// discarded.
+    pub fn drawing_area_pos_to_point(&self, x: f64, _y: f64) -> Option<Point> {
+        let view = self.s.view_read.borrow().get();
+        let x = x - self.s.config.graph_x() as f64;
+        let t = graph_x_to_t(&view, x)
+            .max(0.0).min(view.last_drawn_t as f64)
+            as Time;
+        let pt = self.s.store.borrow().query_point(t).unwrap()?;
+
+        // If we are getting a point >= 10 pixels away, return None instead.
+        // This can happen when old data has been discarded but is still on screen.
+        let pt: Option<Point> = if (pt.t - t) >= (view.zoom_x * 10.0) as Time {
+            None
+        } else {
+            Some(pt)
+        };
+
+        pt
+    }
+}
+
+/// Draw the box for a box zoom drag in progress.
+fn draw_zoom_box(ctx: &cairo::Context, drag: &Drag) {
+    let x = drag.start_x.min(drag.x).floor() + 0.5;
+    let y = drag.start_y.min(drag.y).floor() + 0.5;
+    let w = (drag.start_x - drag.x).abs().floor();
+    let h = (drag.start_y - drag.y).abs().floor();
+    ctx.save();
+    ctx.rectangle(x, y, w, h);
+    ctx.set_source_rgba(ZOOM_BOX_COLOR.0, ZOOM_BOX_COLOR.1,
+                        ZOOM_BOX_COLOR.2, ZOOM_BOX_COLOR.3);
+    ctx.fill_preserve();
+    ctx.set_source_rgba(ZOOM_BOX_OUTLINE_COLOR.0, ZOOM_BOX_OUTLINE_COLOR.1,
+                        ZOOM_BOX_OUTLINE_COLOR.2, ZOOM_BOX_OUTLINE_COLOR.3);
+    ctx.set_line_width(1.0);
+    ctx.stroke();
+    ctx.restore();
+}
+
+/// Handle the graph's draw signal.
+fn graph_draw(_ctrl: &gtk::DrawingArea, ctx: &cairo::Context, s: &State) -> Inhibit {
     trace!("graph_draw");
 
     // Copy from the backing_surface, which was updated elsewhere
-    ctx.rectangle(0.0, 0.0, s.config.graph_width as f64, s.config.graph_height as f64);
+    let graph_x = s.config.graph_x() as f64;
+    ctx.rectangle(graph_x, 0.0,
+                  s.graph_width() as f64, s.drawing_area_height() as f64);
     ctx.set_source_surface(&s.backing_surface.borrow(),
-                           0.0 /* offset x */, 0.0 /* offset y */);
+                           graph_x /* offset x */, 0.0 /* offset y */);
     ctx.fill();
 
+    if s.config.show_value_axis {
+        draw_value_axis(ctx, s, &s.view_read.borrow().get());
+    }
+
+    draw_lane_labels(ctx, s);
+    draw_state_labels(ctx, s);
+
+    if s.config.show_legend {
+        draw_legend(ctx, s);
+    }
+
+    draw_threshold_labels(ctx, s);
+
+    draw_annotations(ctx, s);
+
+    if let Some((t0, t1)) = s.selection_read.borrow().get() {
+        draw_selection(ctx, s, t0, t1);
+    }
+
+    draw_cursors(ctx, s);
+
+    if s.config.show_crosshair {
+        if let Some((x, y)) = s.hover.get() {
+            draw_crosshair(ctx, s, x, y);
+        }
+    }
+
+    if let Some(drag) = s.drag.borrow().as_ref() {
+        if drag.action == DragAction::BoxZoom && drag.moved {
+            draw_zoom_box(ctx, drag);
+        }
+    }
+
     // Calculate FPS, log it once a second.
     s.fps_count.set(s.fps_count.get() + 1);
     let now = Instant::now();
@@ -432,42 +1690,855 @@ fn graph_draw(_ctrl: &gtk::DrawingArea, ctx: &cairo::Context, s: &State) -> Inhi
     Inhibit(false)
 }
 
+/// Draw the value axis to the left of the graph.
+fn draw_value_axis(ctx: &cairo::Context, s: &State, view: &View) {
+    let c = &s.config;
+    let w = VALUE_AXIS_WIDTH as f64;
+
+    ctx.save();
+    set_source_color(ctx, c.axis_background_color);
+    ctx.rectangle(0.0, 0.0, w, s.drawing_area_height() as f64);
+    ctx.fill();
+
+    set_source_color(ctx, c.axis_color);
+    ctx.set_line_width(1.0);
+    set_axis_font(ctx, c);
+    for area in plot_areas(s, view) {
+        if !area.style.plots_values() {
+            continue;
+        }
+        let (v0, v1, h) = (area.value_min, area.value_max, area.h);
+        let top = area.y as f64;
+        for v in value_ticks(v0, v1, value_min_step(v0, v1, h)) {
+            let y = top + gridline_y(v, v0, v1, h);
+            // Keep the label inside the height of the graph or lane.
+            draw_left_tick(ctx, w, y, &v.to_string(), top, top + h as f64);
+        }
+    }
+    ctx.restore();
+}
+
+/// Label the state bars of `LaneStyle::States` lanes with the name of
+/// each state, where there is room.
+fn draw_state_labels(ctx: &cairo::Context, s: &State) {
+    let view = s.view_read.borrow().get();
+    let areas = plot_areas(s, &view);
+    if !areas.iter().any(|area| matches!(area.style, LaneStyle::States(_))) {
+        return;
+    }
+    let graph_x = s.config.graph_x() as f64;
+    let w = s.graph_width() as f64;
+    let t_left = graph_x_to_t(&view, 0.0).max(0.0) as Time;
+    let t_right = view.last_drawn_t;
+    let transitions = s.transitions.borrow();
+
+    ctx.save();
+    set_axis_font(ctx, &s.config);
+    set_source_color(ctx, s.config.axis_color);
+    for area in areas.iter() {
+        let names = match area.style {
+            LaneStyle::States(ref names) => names,
+            _ => continue,
+        };
+        for (i, ch) in area.channels.iter().enumerate() {
+            let tr = match transitions.iter().find(|tr| tr.ch == *ch) {
+                Some(tr) => tr,
+                None => continue,
+            };
+            let (row_y, row_h) = digital_row(area, i, 1);
+            let runs = tr.runs(t_left, t_right);
+            for (j, (start, v)) in runs.iter().enumerate() {
+                let end = runs.get(j + 1).map(|r| r.0).unwrap_or(t_right);
+                let x0 = t_to_graph_x(&view, *start).max(0.0);
+                let x1 = t_to_graph_x(&view, end).min(w);
+                let label = names.get(v).cloned().unwrap_or_else(|| v.to_string());
+                let extents = ctx.text_extents(&label);
+                if extents.x_advance + 4.0 > x1 - x0 {
+                    continue;
+                }
+                ctx.move_to(graph_x + (x0 + x1 - extents.x_advance) / 2.0,
+                            row_y as f64 + (row_h as f64 - extents.height) / 2.0 -
+                            extents.y_bearing);
+                ctx.show_text(&label);
+            }
+        }
+    }
+    ctx.restore();
+}
+
+/// Draw the label of each lane at its top left, and a line between lanes.
+fn draw_lane_labels(ctx: &cairo::Context, s: &State) {
+    let lanes = s.lanes.borrow();
+    let graph_x = s.config.graph_x() as f64;
+    let (w, h) = (s.graph_width() as f64, s.graph_height() as usize);
+    ctx.save();
+    set_axis_font(ctx, &s.config);
+    ctx.set_line_width(1.0);
+    for (i, lane) in lanes.iter().enumerate() {
+        let (y, _) = lane_rect(i, lanes.len(), h);
+        if i > 0 {
+            set_source_color(ctx, s.config.gridline_color);
+            ctx.move_to(graph_x, y as f64 + 0.5);
+            ctx.line_to(graph_x + w, y as f64 + 0.5);
+            ctx.stroke();
+        }
+        if !lane.label.is_empty() {
+            let extents = ctx.text_extents(&lane.label);
+            set_source_color(ctx, s.config.axis_color);
+            ctx.move_to(graph_x + 4.0, y as f64 + 2.0 - extents.y_bearing);
+            ctx.show_text(&lane.label);
+        }
+    }
+    ctx.restore();
+}
+
+/// Draw the legend in the top right corner of the graph.
+fn draw_legend(ctx: &cairo::Context, s: &State) {
+    let store = s.store.borrow();
+    let latest = store.query_point(store.last_t()).unwrap();
+    let cols = s.config.data_source.borrow().get_colors().unwrap();
+    let visible = s.channels_read.borrow().get().visible;
+
+    ctx.save();
+    set_axis_font(ctx, &s.config);
+    let font_extents = ctx.font_extents();
+
+    // Size the legend for the widest possible value, so it doesn't
+    // change size as the latest values change.
+    let text_w = s.channel_names.iter()
+        .map(|name| ctx.text_extents(&format!("{}: {}", name, Value::MAX)).x_advance)
+        .fold(0.0, f64::max);
+    let swatch_size = s.config.axis_font_size;
+    let row_h = font_extents.ascent + font_extents.descent + LEGEND_PADDING;
+    let box_w = swatch_size + text_w + LEGEND_PADDING * 3.0;
+    let box_h = row_h * s.channel_names.len() as f64 + LEGEND_PADDING;
+    let box_x = (s.config.graph_x() + s.graph_width()) as f64 - box_w - LEGEND_MARGIN;
+    let box_y = LEGEND_MARGIN;
+
+    ctx.set_source_rgba(LEGEND_BACKGROUND_COLOR.0,
+                        LEGEND_BACKGROUND_COLOR.1,
+                        LEGEND_BACKGROUND_COLOR.2,
+                        LEGEND_BACKGROUND_COLOR.3);
+    ctx.rectangle(box_x, box_y, box_w, box_h);
+    ctx.fill();
+
+    let mut entries = Vec::with_capacity(s.channel_names.len());
+    for (ch, name) in s.channel_names.iter().enumerate() {
+        let row_y = box_y + LEGEND_PADDING / 2.0 + row_h * ch as f64;
+
+        // Draw the swatch filled if the channel is visible, or just its
+        // outline if it is hidden.
+        set_source_color(ctx, cols[ch % cols.len()]);
+        ctx.rectangle(box_x + LEGEND_PADDING + 0.5,
+                      row_y + (row_h - swatch_size) / 2.0 + 0.5,
+                      swatch_size - 1.0, swatch_size - 1.0);
+        if visible[ch] {
+            ctx.fill();
+        } else {
+            ctx.set_line_width(1.0);
+            ctx.stroke();
+        }
+
+        let label = match latest {
+            Some(ref pt) => format!("{}: {}", name, pt.vals()[ch]),
+            None => name.clone(),
+        };
+        set_source_color_alpha(ctx, s.config.axis_color,
+                               if visible[ch] { 1.0 } else { LEGEND_HIDDEN_ALPHA });
+        ctx.move_to(box_x + LEGEND_PADDING * 2.0 + swatch_size,
+                    row_y + LEGEND_PADDING / 2.0 + font_extents.ascent);
+        ctx.show_text(&label);
+
+        entries.push(LegendEntry {
+            ch,
+            x: box_x,
+            y: row_y,
+            w: box_w,
+            h: row_h,
+        });
+    }
+    *s.legend_entries.borrow_mut() = entries;
+    ctx.restore();
+}
+
+/// Label the threshold lines of visible channels, at the right edge of the graph.
+fn draw_threshold_labels(ctx: &cairo::Context, s: &State) {
+    let view = s.view_read.borrow().get();
+    let graph_x = s.config.graph_x() as f64;
+    let w = s.graph_width() as f64;
+    ctx.save();
+    set_axis_font(ctx, &s.config);
+    for area in plot_areas(s, &view) {
+        if !area.style.plots_values() {
+            continue;
+        }
+        for th in s.thresholds.borrow().iter() {
+            if th.label.is_empty() || !area.channels.contains(&th.channel) ||
+                th.value < area.value_min || th.value > area.value_max {
+                continue;
+            }
+            let y = area.y as f64 +
+                gridline_y(th.value, area.value_min, area.value_max, area.h);
+            let extents = ctx.text_extents(&th.label);
+            set_source_color(ctx, th.color);
+            ctx.move_to(graph_x + w - extents.x_advance - 4.0,
+                        (y - 3.0).max(area.y as f64 - extents.y_bearing));
+            ctx.show_text(&th.label);
+        }
+    }
+    ctx.restore();
+}
+
+/// Draw the annotations that are within the graph.
+fn draw_annotations(ctx: &cairo::Context, s: &State) {
+    let view = s.view_read.borrow().get();
+    let graph_x = s.config.graph_x() as f64;
+    let (w, h) = (s.graph_width() as f64, s.graph_height() as f64);
+    let t_left = graph_x_to_t(&view, 0.0).max(0.0) as Time;
+    let t_right = graph_x_to_t(&view, w).max(0.0) as Time;
+    let annotations = s.store.borrow().query_annotations(t_left, t_right);
+    if annotations.is_empty() {
+        return;
+    }
+
+    ctx.save();
+    ctx.rectangle(graph_x, 0.0, w, h);
+    ctx.clip();
+    ctx.set_line_width(1.0);
+    set_axis_font(ctx, &s.config);
+    let font_extents = ctx.font_extents();
+    let row_h = font_extents.ascent + font_extents.descent + 2.0;
+    for (i, a) in annotations.iter() {
+        let x0 = t_to_graph_x(&view, a.t0).floor();
+        let label_x = match a.t1 {
+            None => {
+                set_source_color(ctx, a.color);
+                ctx.move_to(graph_x + x0 + 0.5, 0.0);
+                ctx.line_to(graph_x + x0 + 0.5, h);
+                ctx.stroke();
+                x0 + 3.0
+            },
+            Some(t1) => {
+                let x0 = x0.max(0.0);
+                let x1 = (t_to_graph_x(&view, t1).floor() + 1.0).min(w);
+                set_source_color_alpha(ctx, a.color, ANNOTATION_SPAN_ALPHA);
+                ctx.rectangle(graph_x + x0, 0.0, x1 - x0, h);
+                ctx.fill();
+                x0 + 3.0
+            },
+        };
+        // Below the measurement cursor labels. Rows are chosen by the
+        // order annotations were added in, so a label keeps its row as
+        // others scroll off.
+        let row = (i % ANNOTATION_LABEL_ROWS) as f64 + 1.0;
+        set_source_color(ctx, a.color);
+        ctx.move_to(graph_x + label_x, row_h * row + font_extents.ascent + 2.0);
+        ctx.show_text(&a.text);
+    }
+    ctx.restore();
+}
+
+/// Shade the selected time range from t0 to t1.
+fn draw_selection(ctx: &cairo::Context, s: &State, t0: Time, t1: Time) {
+    let view = s.view_read.borrow().get();
+    let graph_x = s.config.graph_x() as f64;
+    let w = s.graph_width() as f64;
+    let x0 = t_to_graph_x(&view, t0).floor().max(0.0);
+    // Always shade at least one pixel, so a short selection is visible.
+    let x1 = (t_to_graph_x(&view, t1).floor() + 1.0).min(w);
+    if x1 <= x0 {
+        return;
+    }
+    ctx.save();
+    ctx.set_source_rgba(SELECTION_COLOR.0, SELECTION_COLOR.1,
+                        SELECTION_COLOR.2, SELECTION_COLOR.3);
+    ctx.rectangle(graph_x + x0, 0.0, x1 - x0, s.graph_height() as f64);
+    ctx.fill();
+    ctx.restore();
+}
+
+/// Set the selected time range from t0 to t1, where t0 <= t1.
+fn set_selection(s: &State, t0: Time, t1: Time) {
+    s.selection_write.borrow_mut().set(&Some((t0, t1)));
+    s.drawing_area.queue_draw();
+}
+
+/// Returns the time range selected by a drag from `drag_start_x` in
+/// `start_view` to `x` in the current view.
+fn drag_selection(g: &Graph, drag_start_x: f64, start_view: &View, x: f64) -> (Time, Time) {
+    let graph_x = g.s.config.graph_x() as f64;
+    let w = g.s.graph_width() as f64;
+    let t_start = graph_x_to_t(start_view, (drag_start_x - graph_x).max(0.0).min(w));
+    let t_end = graph_x_to_t(&g.view(), (x - graph_x).max(0.0).min(w));
+    let (t0, t1) = (t_start.min(t_end).max(0.0), t_start.max(t_end).max(0.0));
+    (t0 as Time, t1 as Time)
+}
+
+/// Draw the measurement cursors that are within the graph.
+fn draw_cursors(ctx: &cairo::Context, s: &State) {
+    let cursors = s.cursors_read.borrow().get();
+    let view = s.view_read.borrow().get();
+    let graph_x = s.config.graph_x() as f64;
+    let (w, h) = (s.graph_width() as f64, s.graph_height() as f64);
+
+    ctx.save();
+    ctx.set_line_width(1.0);
+    set_axis_font(ctx, &s.config);
+    let font_extents = ctx.font_extents();
+    for (i, (cursor, label)) in [(Cursor::A, "A"), (Cursor::B, "B")].iter().enumerate() {
+        let t = match cursors.get(*cursor) {
+            Some(t) => t,
+            None => continue,
+        };
+        let x = t_to_graph_x(&view, t);
+        if x < 0.0 || x >= w {
+            continue;
+        }
+        let x = graph_x + x.floor() + 0.5;
+        let col = CURSOR_COLORS[i];
+        ctx.set_source_rgb(col.0, col.1, col.2);
+        ctx.move_to(x, 0.0);
+        ctx.line_to(x, h);
+        ctx.stroke();
+        ctx.move_to(x + 3.0, font_extents.ascent + 2.0);
+        ctx.show_text(label);
+    }
+    ctx.restore();
+}
+
+/// Place or remove a measurement cursor, and update the cursors observable.
+fn set_cursor(s: &State, cursor: Cursor, t: Option<Time>) {
+    let old = s.cursors_read.borrow().get();
+    let (a, b) = match cursor {
+        Cursor::A => (t, old.b),
+        Cursor::B => (old.a, t),
+    };
+    let cursors = Cursors::new(&s.store.borrow(), s.config.wall_clock.as_ref(), a, b);
+    s.cursors_write.borrow_mut().set(&cursors);
+    s.drawing_area.queue_draw();
+}
+
+/// Recompute the measurement cursors' values after data is ingested or
+/// discarded, so they follow the data at their times.
+fn update_cursors(s: &State) {
+    let old = s.cursors_read.borrow().get();
+    if old.a.is_none() && old.b.is_none() {
+        return;
+    }
+    let cursors = old.refresh(&s.store.borrow(), s.config.wall_clock.as_ref());
+    if cursors != old {
+        s.cursors_write.borrow_mut().set(&cursors);
+        s.drawing_area.queue_draw();
+    }
+}
+
+/// Place a measurement cursor at position x on `drawing_area`, at the
+/// time of the nearest point if there is one close by.
+fn place_cursor_at(s: &State, cursor: Cursor, x: f64) {
+    let view = s.view_read.borrow().get();
+    let x = x - s.config.graph_x() as f64;
+    if x < 0.0 || x >= s.graph_width() as f64 {
+        return;
+    }
+    let t = graph_x_to_t(&view, x).max(0.0);
+    let t = match s.store.borrow().query_nearest(t as Time).unwrap() {
+        Some(pt) if (pt.t as f64 - t).abs() <= HOVER_MAX_DISTANCE * view.zoom_x => pt.t,
+        _ => t as Time,
+    };
+    set_cursor(s, cursor, Some(t));
+}
+
+/// Draw the hover crosshair at x, y on `drawing_area`, with a tooltip
+/// showing the time and values of the nearest point.
+fn draw_crosshair(ctx: &cairo::Context, s: &State, x: f64, y: f64) {
+    let graph_x = s.config.graph_x() as f64;
+    let (w, h) = (s.graph_width() as f64, s.graph_height() as f64);
+    if x < graph_x || x >= graph_x + w || y < 0.0 || y >= h {
+        return;
+    }
+
+    ctx.save();
+    set_source_color(ctx, s.config.axis_color);
+    ctx.set_line_width(1.0);
+    ctx.move_to(x.floor() + 0.5, 0.0);
+    ctx.line_to(x.floor() + 0.5, h);
+    ctx.stroke();
+
+    let view = s.view_read.borrow().get();
+    let t = graph_x_to_t(&view, x - graph_x)
+        .max(0.0).min(view.last_drawn_t as f64);
+    let pt = match s.store.borrow().query_nearest(t as Time).unwrap() {
+        Some(pt) if (pt.t as f64 - t).abs() <= HOVER_MAX_DISTANCE * view.zoom_x => pt,
+        _ => {
+            ctx.restore();
+            return;
+        },
+    };
+
+    // Mark the point on each visible channel.
+    let cols = s.config.data_source.borrow().get_colors().unwrap();
+    let channels = s.channels_read.borrow().get();
+    let pt_x = graph_x + t_to_graph_x(&view, pt.t);
+    for area in plot_areas(s, &view) {
+        if !area.style.plots_values() {
+            continue;
+        }
+        for &ch in area.channels.iter() {
+            if let Some(pt_y) = value_to_y(pt.vals()[ch], area.value_min, area.value_max,
+                                           area.h) {
+                set_source_color(ctx, cols[ch % cols.len()]);
+                ctx.arc(pt_x.floor() + 0.5, (area.y + pt_y) as f64 + 0.5,
+                        CROSSHAIR_MARKER_RADIUS, 0.0, 2.0 * std::f64::consts::PI);
+                ctx.stroke();
+            }
+        }
+    }
+
+    let mut rows = vec![(None, s.format_time(pt.t))];
+    for (ch, name) in s.channel_names.iter().enumerate() {
+        if !channels.visible[ch] {
+            continue;
+        }
+        let unit = &s.channel_units[ch];
+        let label = if unit.is_empty() {
+            format!("{}: {}", name, pt.vals()[ch])
+        } else {
+            format!("{}: {} {}", name, pt.vals()[ch], unit)
+        };
+        rows.push((Some(cols[ch % cols.len()]), label));
+    }
+    draw_tooltip(ctx, s, x, y, &rows);
+    ctx.restore();
+}
+
+/// Draw a tooltip next to x, y on `drawing_area`, kept inside the
+/// graph. Each row is a line of text, with a color swatch if it has a
+/// color.
+fn draw_tooltip(ctx: &cairo::Context, s: &State, x: f64, y: f64,
+                rows: &[(Option<Color>, String)]) {
+    ctx.save();
+    set_axis_font(ctx, &s.config);
+    let font_extents = ctx.font_extents();
+    let swatch_size = s.config.axis_font_size;
+    let text_w = rows.iter()
+        .map(|(_, label)| ctx.text_extents(label).x_advance)
+        .fold(0.0, f64::max);
+    let row_h = font_extents.ascent + font_extents.descent + LEGEND_PADDING;
+    let box_w = swatch_size + text_w + LEGEND_PADDING * 3.0;
+    let box_h = row_h * rows.len() as f64 + LEGEND_PADDING;
+
+    // Put the tooltip below and to the right of the pointer, unless
+    // that would go past the edge of the graph.
+    let graph_x = s.config.graph_x() as f64;
+    let (w, h) = (s.graph_width() as f64, s.graph_height() as f64);
+    let box_x = if x + TOOLTIP_OFFSET + box_w <= graph_x + w {
+        x + TOOLTIP_OFFSET
+    } else {
+        (x - TOOLTIP_OFFSET - box_w).max(graph_x)
+    };
+    let box_y = if y + TOOLTIP_OFFSET + box_h <= h {
+        y + TOOLTIP_OFFSET
+    } else {
+        (y - TOOLTIP_OFFSET - box_h).max(0.0)
+    };
+
+    ctx.set_source_rgba(LEGEND_BACKGROUND_COLOR.0,
+                        LEGEND_BACKGROUND_COLOR.1,
+                        LEGEND_BACKGROUND_COLOR.2,
+                        LEGEND_BACKGROUND_COLOR.3);
+    ctx.rectangle(box_x, box_y, box_w, box_h);
+    ctx.fill();
+
+    for (i, (col, label)) in rows.iter().enumerate() {
+        let row_y = box_y + LEGEND_PADDING / 2.0 + row_h * i as f64;
+        if let Some(col) = col {
+            set_source_color(ctx, *col);
+            ctx.rectangle(box_x + LEGEND_PADDING,
+                          row_y + (row_h - swatch_size) / 2.0,
+                          swatch_size, swatch_size);
+            ctx.fill();
+        }
+        set_source_color(ctx, s.config.axis_color);
+        ctx.move_to(box_x + LEGEND_PADDING * 2.0 + swatch_size,
+                    row_y + LEGEND_PADDING / 2.0 + font_extents.ascent);
+        ctx.show_text(label);
+    }
+    ctx.restore();
+}
+
+/// Handle the drawing area's size changing: resize the backing
+/// surfaces to match and redraw the graph at the new size.
+///
+/// The time most recently drawn stays at the right edge of the graph,
+/// so the current view stays anchored as the graph grows or shrinks.
+fn drawing_area_size_allocate(s: &State, allocation: &gtk::Allocation) {
+    let graph_width = (allocation.width - s.config.graph_x() as i32).max(1) as u32;
+    let graph_height = (allocation.height - s.config.time_axis_height() as i32).max(1) as u32;
+    if graph_width == s.graph_width() && graph_height == s.graph_height() {
+        return;
+    }
+    debug!("drawing_area_size_allocate graph_width={} graph_height={}",
+           graph_width, graph_height);
+
+    s.graph_width.set(graph_width);
+    s.graph_height.set(graph_height);
+    recreate_surfaces(s);
+    redraw_graph(s);
+}
+
+/// Handle the drawing area's scale factor changing, e.g. when the
+/// window moves to a display with a different DPI: re-render at the
+/// new device resolution.
+fn drawing_area_scale_factor_changed(s: &State, scale_factor: i32) {
+    if scale_factor == s.scale_factor.get() {
+        return;
+    }
+    debug!("drawing_area_scale_factor_changed scale_factor={}", scale_factor);
+    s.scale_factor.set(scale_factor);
+    recreate_surfaces(s);
+    redraw_graph(s);
+}
+
+/// Replace the backing surfaces to match the current graph size and
+/// scale factor. The caller should then redraw the graph.
+fn recreate_surfaces(s: &State) {
+    *s.backing_surface.borrow_mut() =
+        create_backing_surface(&s.gdk_window, s.graph_width(), s.drawing_area_height(),
+                               s.scale_factor.get());
+    *s.temp_surface.borrow_mut() =
+        create_backing_surface(&s.gdk_window, s.graph_width(), s.drawing_area_height(),
+                               s.scale_factor.get());
+}
+
+/// Take the next step of the zoom animation, if one is running.
+fn step_zoom_animation(g: &Graph) {
+    let (zoom_x, x, done) = match g.s.zoom_animation.borrow().as_ref() {
+        None => return,
+        Some(a) => {
+            let frac = a.start.elapsed().as_secs_f64() /
+                g.s.config.zoom_animation_duration.as_secs_f64();
+            // Interpolate geometrically, so each frame zooms by the same factor.
+            let zoom_x = if frac >= 1.0 {
+                a.to_zoom_x
+            } else {
+                a.from_zoom_x * (a.to_zoom_x / a.from_zoom_x).powf(frac)
+            };
+            (zoom_x, a.x, frac >= 1.0)
+        },
+    };
+    if done {
+        g.s.zoom_animation.replace(None);
+    }
+    match x {
+        Some(x) => g.set_zoom_x_around(zoom_x, x),
+        None => g.set_zoom_x(zoom_x),
+    }
+}
+
+/// Returns the time at x pixels from the left edge of the graph in `view`.
+fn graph_x_to_t(view: &View, x: f64) -> f64 {
+    view.last_drawn_t as f64 + (x - view.last_drawn_x as f64) * view.zoom_x
+}
+
+/// Returns the x position from the left edge of the graph of time `t`
+/// in `view`.
+fn t_to_graph_x(view: &View, t: Time) -> f64 {
+    view.last_drawn_x as f64 + (t as f64 - view.last_drawn_t as f64) / view.zoom_x
+}
+
+/// Handle a button press on the graph's drawing area.
+fn drawing_area_button_press(g: &Graph, ev: &gdk::EventButton) -> Inhibit {
+    if ev.get_event_type() != gdk::EventType::ButtonPress {
+        return Inhibit(false);
+    }
+
+    let s = &g.s;
+    let button = ev.get_button();
+    let (x, y) = ev.get_position();
+    if button == 1 {
+        let legend_ch = s.legend_entries.borrow().iter()
+            .find(|e| e.contains(x, y))
+            .map(|e| e.ch);
+        if let Some(ch) = legend_ch {
+            let mut channels = s.channels_read.borrow().get();
+            channels.visible[ch] = !channels.visible[ch];
+            set_channels(s, &channels);
+            // Stop other handlers treating this as a click on the data.
+            return Inhibit(true);
+        }
+    }
+
+    if button == 3 && s.config.place_cursors_on_click {
+        place_cursor_at(s, Cursor::B, x);
+        return Inhibit(true);
+    }
+
+    let alt = ev.get_state().contains(gdk::ModifierType::MOD1_MASK);
+    let action = match button {
+        1 if alt => DragAction::BoxZoom,
+        1 => s.drag_action.get(),
+        2 => DragAction::Pan,
+        _ => return Inhibit(false),
+    };
+    *s.drag.borrow_mut() = Some(Drag {
+        button,
+        action,
+        start_x: x,
+        start_y: y,
+        start_view: g.view(),
+        x,
+        y,
+        moved: false,
+    });
+
+    Inhibit(false)
+}
+
+/// Handle the pointer moving over the graph's drawing area.
+fn drawing_area_motion_notify(g: &Graph, ev: &gdk::EventMotion) -> Inhibit {
+    let (x, y) = ev.get_position();
+    if g.s.config.show_crosshair {
+        // Only the overlay needs redrawing, not the backing surface.
+        g.s.hover.set(Some((x, y)));
+        g.s.drawing_area.queue_draw();
+    }
+
+    let (action, start_x, start_view) = {
+        // Scope the mutable borrow of drag.
+        let mut drag = g.s.drag.borrow_mut();
+        let drag = match drag.as_mut() {
+            Some(drag) => drag,
+            None => return Inhibit(false),
+        };
+        if !drag.moved &&
+            (x - drag.start_x).abs() < DRAG_THRESHOLD &&
+            (y - drag.start_y).abs() < DRAG_THRESHOLD {
+            return Inhibit(false);
+        }
+        drag.moved = true;
+        drag.x = x;
+        drag.y = y;
+        (drag.action, drag.start_x, drag.start_view.clone())
+    };
+
+    match action {
+        DragAction::None => (),
+        DragAction::BoxZoom => g.s.drawing_area.queue_draw(),
+        DragAction::Select => {
+            let (t0, t1) = drag_selection(g, start_x, &start_view, x);
+            set_selection(&g.s, t0, t1);
+        },
+        DragAction::Pan => {
+            // Move the time under the pointer at the start of the drag
+            // to follow the pointer.
+            let width = g.s.graph_width() as f64;
+            let zoom_x = start_view.zoom_x;
+            let start_right_t = graph_x_to_t(&start_view, width);
+            let new_right_t = start_right_t - (x - start_x) * zoom_x;
+            g.scroll(new_right_t - width * zoom_x);
+        },
+    }
+
+    Inhibit(false)
+}
+
+/// Handle the pointer leaving the graph's drawing area.
+fn drawing_area_leave_notify(g: &Graph) -> Inhibit {
+    if g.s.hover.replace(None).is_some() {
+        g.s.drawing_area.queue_draw();
+    }
+    Inhibit(false)
+}
+
+/// Handle a button release on the graph's drawing area.
+fn drawing_area_button_release(g: &Graph, ev: &gdk::EventButton) -> Inhibit {
+    let button = ev.get_button();
+    let drag = {
+        // Scope the mutable borrow of drag.
+        let mut drag = g.s.drag.borrow_mut();
+        match drag.as_ref() {
+            Some(d) if d.button == button => drag.take().unwrap(),
+            _ => return Inhibit(false),
+        }
+    };
+
+    let (x, y) = ev.get_position();
+    match drag.end() {
+        DragEnd::Click => {
+            if g.s.config.place_cursors_on_click {
+                place_cursor_at(&g.s, Cursor::A, x);
+            }
+            g.s.click_signal.borrow().raise((x, y));
+        },
+        DragEnd::Select => {
+            let (t0, t1) = drag_selection(g, drag.start_x, &drag.start_view, x);
+            set_selection(&g.s, t0, t1);
+            g.s.selection_signal.borrow().raise((t0, t1));
+        },
+        DragEnd::BoxZoom => {
+            box_zoom(g, &Drag { x, y, .. drag });
+            // Remove the box.
+            g.s.drawing_area.queue_draw();
+        },
+        DragEnd::Nothing => (),
+    }
+
+    Inhibit(false)
+}
+
+/// Zoom to the box drawn by a box zoom drag.
+fn box_zoom(g: &Graph, drag: &Drag) {
+    let s = &g.s;
+    let view = g.view();
+    let width = s.graph_width() as f64;
+    let graph_x = s.config.graph_x() as f64;
+    let x0 = (drag.start_x.min(drag.x) - graph_x).max(0.0).min(width);
+    let x1 = (drag.start_x.max(drag.x) - graph_x).max(0.0).min(width);
+    if x1 - x0 < 1.0 {
+        return;
+    }
+    let t0 = graph_x_to_t(&view, x0).max(0.0) as Time;
+    let t1 = graph_x_to_t(&view, x1).max(0.0) as Time;
+    if t1 <= t0 {
+        return;
+    }
+
+    // Only zoom the values if the box has some height, so a
+    // horizontal drag just zooms time. Lanes have their own value
+    // ranges, so only time is zoomed.
+    let h = s.graph_height() as usize;
+    let values = if (drag.start_y - drag.y).abs() >= DRAG_THRESHOLD &&
+        s.lanes.borrow().is_empty() {
+        let v0 = y_to_value(drag.start_y.max(drag.y), view.value_min, view.value_max, h);
+        let v1 = y_to_value(drag.start_y.min(drag.y), view.value_min, view.value_max, h);
+        Some((v0, v1))
+    } else {
+        None
+    };
+
+    g.zoom_to(t0, t1, values);
+}
+
+/// Handle mouse wheel scrolling on the graph's drawing area.
+fn drawing_area_scroll(g: &Graph, ev: &gdk::EventScroll) -> Inhibit {
+    if !g.s.config.mouse_wheel_zoom {
+        return Inhibit(false);
+    }
+
+    // Positive steps zoom out.
+    let steps = match ev.get_direction() {
+        gdk::ScrollDirection::Up => -1.0,
+        gdk::ScrollDirection::Down => 1.0,
+        gdk::ScrollDirection::Smooth => ev.get_delta().1,
+        _ => return Inhibit(false),
+    };
+    if steps == 0.0 {
+        return Inhibit(false);
+    }
+
+    let (x, _y) = ev.get_position();
+    let new_zoom_x = g.target_zoom_x() * WHEEL_ZOOM_FACTOR.powf(steps);
+    g.animate_zoom_x(new_zoom_x, Some(x));
+    Inhibit(true)
+}
+
+/// Update how the channels are drawn and redraw the graph.
+fn set_channels(s: &State, channels: &Channels) {
+    s.channels_write.borrow_mut().set(channels);
+    redraw_graph(s);
+}
+
+/// Try to scroll the graph from `old_view` to `new_view` by moving the
+/// part of the backing surface that is still valid, and rendering only
+/// the newly exposed strip.
+///
+/// Returns false if the backing surface can't be reused, e.g. the
+/// graph was not fully drawn or the views don't line up on a whole
+/// pixel; the caller should then redraw the whole graph.
+fn scroll_backing_surface(s: &State, old_view: &View, new_view: &View) -> bool {
+    let width = s.graph_width() as i64;
+    let zoom_x = new_view.zoom_x;
+    if old_view.zoom_x != zoom_x || old_view.last_drawn_x != s.graph_width() ||
+        old_view.value_min != new_view.value_min ||
+        old_view.value_max != new_view.value_max {
+        return false;
+    }
+    let (old_px, new_px) = match (pixel_index(old_view.last_drawn_t, zoom_x),
+                                  pixel_index(new_view.last_drawn_t, zoom_x)) {
+        (Some(old_px), Some(new_px)) => (old_px, new_px),
+        _ => return false,
+    };
+    let shift = new_px - old_px;
+    trace!("scroll_backing_surface shift={}", shift);
+    if shift.abs() >= width {
+        return false;
+    }
+    if shift == 0 {
+        return true;
+    }
+
+    blit_backing_surface(s, -shift);
+
+    let (x, px0, px1) = if shift > 0 {
+        // Scrolled forward in time: render the new strip at the right.
+        ((width - shift) as usize, old_px, new_px)
+    } else {
+        // Scrolled back in time: render the new strip at the left.
+        (0, new_px - width, old_px - width)
+    };
+    draw_patch(s, &s.backing_surface.borrow(), x, px0, px1, new_view);
+    s.drawing_area.queue_draw();
+    true
+}
+
+/// Move the contents of the backing surface `dx` pixels to the right
+/// (left if negative). The area uncovered is left with stale contents
+/// for the caller to draw over.
+fn blit_backing_surface(s: &State, dx: i64) {
+    // Copy existing graph to the temp surface with the offset.
+    {
+        let c = cairo::Context::new(&s.temp_surface.borrow());
+        c.set_source_surface(&s.backing_surface.borrow(),
+                             dx as f64 /* x offset*/, 0.0 /* y offset */);
+        c.rectangle(dx.max(0) as f64, // x offset
+                    0.0, // y offset
+                    (s.graph_width() as i64 - dx.abs()) as f64, // width
+                    s.drawing_area_height() as f64); // height
+        c.fill();
+    }
+
+    // Present new graph by swapping the surfaces.
+    s.backing_surface.swap(&s.temp_surface);
+}
+
 /// Redraw the whole graph to the backing store
 fn redraw_graph(s: &State) {
     trace!("redraw_graph");
     let backing_surface = s.backing_surface.borrow();
     {
         // Clear backing_surface
-        let c = cairo::Context::new(&*backing_surface);
+        let c = cairo::Context::new(&backing_surface);
         c.set_source_rgb(BACKGROUND_COLOR.0,
                          BACKGROUND_COLOR.1,
                          BACKGROUND_COLOR.2);
-        c.rectangle(0.0, 0.0, s.config.graph_width as f64, s.config.graph_height as f64);
+        c.rectangle(0.0, 0.0,
+                    s.graph_width() as f64, s.drawing_area_height() as f64);
         c.fill();
     }
 
     let mut view = s.view_read.borrow().get();
-    let cols = s.config.data_source.borrow().get_colors().unwrap();
-    let t1: u32 = view.last_drawn_t;
-    let t0: u32 = (t1 as i64 - (s.config.graph_width as f64 * view.zoom_x) as i64).max(0) as u32;
-    let patch_dims = ((((t1-t0) as f64 / view.zoom_x).floor() as u32)
-                          .min(s.config.graph_width) as usize,
-                      s.config.graph_height as usize);
-    if patch_dims.0 > 0 {
+    let px1 = t_to_pixel(view.last_drawn_t, view.zoom_x);
+    let w = px1.min(s.graph_width() as i64) as usize;
+    if w > 0 {
         let x = match view.mode {
-            ViewMode::Following => (s.config.graph_width as usize) - patch_dims.0,
+            ViewMode::Following | ViewMode::Paused | ViewMode::Triggered =>
+                (s.graph_width() as usize) - w,
             ViewMode::Scrolled => 0,
         };
-        render_patch(&*backing_surface,
-                     &s.store.borrow(),
-                     &cols,
-                     patch_dims.0 /* w */, patch_dims.1 /* h */,
-                     x /* x */, 0 /* y */,
-                     t0, t1,
-                     0 /* v0 */, std::u16::MAX /* v1 */,
-                     s.config.point_style);
-        view.last_drawn_x = (x + patch_dims.0) as u32;
-        view.last_drawn_t = t1;
+        draw_patch(s, &backing_surface,
+                   x, px1 - w as i64, px1, &view);
+        view.last_drawn_x = (x + w) as u32;
+        view.last_drawn_t = pixel_to_t(px1, view.zoom_x);
         s.view_write.borrow_mut().set(&view);
     }
     s.drawing_area.queue_draw();
@@ -477,19 +2548,39 @@ fn tick(s: &State) {
     trace!("tick");
     // Ingest new data
     let new_data = s.config.data_source.borrow_mut().get_data().unwrap();
+    let new_annotations = s.config.data_source.borrow_mut().get_annotations().unwrap();
+    if !new_annotations.is_empty() {
+        let mut store = s.store.borrow_mut();
+        for a in new_annotations {
+            store.add_annotation(a);
+        }
+        s.drawing_area.queue_draw();
+    }
 
-
-    if new_data.len() > 0 {
-        s.store.borrow_mut().ingest(&*new_data).unwrap();
+    if !new_data.is_empty() {
+        s.store.borrow_mut().ingest(&new_data).unwrap();
+        let crossings = detect_crossings(&s.thresholds.borrow(),
+                                         &mut s.beyond_thresholds.borrow_mut(),
+                                         &new_data);
+        for c in crossings {
+            s.crossing_signal.borrow().raise(c);
+        }
+        for tr in s.transitions.borrow_mut().iter_mut() {
+            tr.ingest(&new_data);
+        }
         let t_latest = s.store.borrow().last_t();
 
         // Discard old data if there is any
-        let window_base_dt = (s.config.graph_width as f64 * s.config.base_zoom_x) as u32;
-        let keep_window = s.config.windows_to_store * window_base_dt;
-        let discard_start = if t_latest >= keep_window { t_latest - keep_window } else { 0 };
+        let window_base_dt = (s.graph_width() as f64 * s.config.base_zoom_x) as Time;
+        let keep_window = s.config.windows_to_store as Time * window_base_dt;
+        let discard_start = t_latest.saturating_sub(keep_window);
         if discard_start > 0 {
             s.store.borrow_mut().discard(0, discard_start).unwrap();
+            for tr in s.transitions.borrow_mut().iter_mut() {
+                tr.discard_before(discard_start);
+            }
         }
+        update_cursors(s);
 
         let mut view = s.view_read.borrow().get();
 
@@ -497,57 +2588,59 @@ fn tick(s: &State) {
         view.max_t = t_latest;
         s.view_write.borrow_mut().set(&view);
 
+        if view.mode == ViewMode::Triggered {
+            tick_triggered(s, &new_data, view);
+            return;
+        }
+
+        let drawn_px = t_to_pixel(view.last_drawn_t, view.zoom_x);
+        let latest_px = t_to_pixel(t_latest, view.zoom_x);
+        if view.mode == ViewMode::Following &&
+            latest_px - drawn_px >= s.graph_width() as i64 {
+            // None of the drawn data is still in the window, e.g. this is
+            // the first data received and it doesn't start near 0, so
+            // redraw the whole graph at the latest time.
+            view.last_drawn_t = t_latest;
+            s.view_write.borrow_mut().set(&view);
+            redraw_graph(s);
+            return;
+        }
+
         if view.mode == ViewMode::Following ||
-            (view.mode == ViewMode::Scrolled && view.last_drawn_x < s.config.graph_width) {
+            (view.mode == ViewMode::Scrolled && view.last_drawn_x < s.graph_width()) {
 
             // Draw the new data.
 
-            // Calculate the size of the latest patch to render.
-            // TODO: Handle when patch_dims.0 >= s.config.graph_width.
-            // TODO: Handle scrolled when new data is offscreen (don't draw)
-            let patch_dims =
-                ((((t_latest - view.last_drawn_t) as f64 / view.zoom_x)
-                  .floor() as usize)
-                 .min(s.config.graph_width as usize),
-                 s.config.graph_height as usize);
+            // Calculate the width of the latest patch to render: the
+            // whole pixels of data since we last drew, or when scrolled
+            // up to the right edge of the graph.
+            let following = view.mode == ViewMode::Following;
+            let max_w = if following {
+                s.graph_width()
+            } else {
+                s.graph_width() - view.last_drawn_x
+            };
+            let w = (latest_px - drawn_px).min(max_w as i64).max(0);
             // If there is more than a pixel's worth of data to render since we last drew,
             // then draw it.
-            if patch_dims.0 > 0 {
-                let new_t = view.last_drawn_t + (patch_dims.0 as f64 * view.zoom_x) as u32;
-
-                let patch_offset_x = match view.mode {
-                    ViewMode::Following => s.config.graph_width - (patch_dims.0 as u32),
-                    ViewMode::Scrolled => view.last_drawn_x,
+            if w > 0 {
+                let patch_offset_x = if following {
+                    s.graph_width() - (w as u32)
+                } else {
+                    view.last_drawn_x
                 };
 
-                if view.mode == ViewMode::Following {
-                    // Copy existing graph to the temp surface, offsetting it to the left.
-                    let c = cairo::Context::new(&*s.temp_surface.borrow());
-                    c.set_source_surface(&*s.backing_surface.borrow(),
-                                         -(patch_dims.0 as f64) /* x offset*/, 0.0 /* y offset */);
-                    c.rectangle(0.0, // x offset
-                                0.0, // y offset
-                                patch_offset_x as f64, // width
-                                s.config.graph_height as f64); // height
-                    c.fill();
-
-                    // Present new graph by swapping the surfaces.
-                    s.backing_surface.swap(&s.temp_surface);
+                if following {
+                    // Move the existing graph to the left to make room for the patch.
+                    blit_backing_surface(s, -w);
                 }
 
-                let cols = s.config.data_source.borrow().get_colors().unwrap();
-                render_patch(&s.backing_surface.borrow(),
-                             &s.store.borrow(),
-                             &cols,
-                             patch_dims.0 /* w */, patch_dims.1 /* h */,
-                             patch_offset_x as usize, 0 /* y */,
-                             view.last_drawn_t, new_t,
-                             0 /* v0 */, std::u16::MAX /* v1 */,
-                             s.config.point_style);
-
-                view.last_drawn_t = new_t;
-                view.last_drawn_x = (patch_offset_x + patch_dims.0 as u32)
-                                    .min(s.config.graph_width);
+                draw_patch(s, &s.backing_surface.borrow(),
+                           patch_offset_x as usize, drawn_px, drawn_px + w, &view);
+
+                view.last_drawn_t = pixel_to_t(drawn_px + w, view.zoom_x);
+                view.last_drawn_x = (patch_offset_x + w as u32)
+                                    .min(s.graph_width());
                 s.view_write.borrow_mut().set(&view);
             }
 
@@ -557,55 +2650,339 @@ fn tick(s: &State) {
     }
 }
 
+/// Check newly ingested data for a trigger, and draw a sweep when one
+/// is ready.
+fn tick_triggered(s: &State, new_data: &[Point], mut view: View) {
+    let window_dt = s.graph_width() as f64 * view.zoom_x;
+    let end_t = match s.trigger.borrow_mut().as_mut() {
+        Some(ts) => {
+            let pre_dt = window_dt * ts.trigger.pre_trigger.clamp(0.0, 1.0);
+            ts.update(new_data, pre_dt as Time, (window_dt - pre_dt) as Time)
+        },
+        None => None,
+    };
+    if let Some(end_t) = end_t {
+        trace!("tick_triggered sweep end_t={}", end_t);
+        view.last_drawn_t = end_t;
+        s.view_write.borrow_mut().set(&view);
+        redraw_graph(s);
+    }
+}
+
+/// Returns the areas to draw channels in: one for each lane, or the
+/// whole graph if there are no lanes.
+fn plot_areas(s: &State, view: &View) -> Vec<PlotArea> {
+    let to_draw = s.channels_read.borrow().get().to_draw();
+    let lanes = s.lanes.borrow();
+    let h = s.graph_height() as usize;
+    if lanes.is_empty() {
+        return vec![PlotArea {
+            y: 0,
+            h,
+            channels: to_draw,
+            value_min: view.value_min,
+            value_max: view.value_max,
+            style: match *s.spectrogram.borrow() {
+                Some(ref sg) => LaneStyle::Spectrogram(sg.clone()),
+                None => LaneStyle::Analog,
+            },
+        }];
+    }
+    lanes.iter().enumerate()
+        .map(|(i, lane)| {
+            let (y, lane_h) = lane_rect(i, lanes.len(), h);
+            PlotArea {
+                y,
+                h: lane_h,
+                channels: to_draw.iter().copied()
+                    .filter(|ch| lane.channels.contains(ch))
+                    .collect(),
+                value_min: lane.value_min,
+                value_max: lane.value_max,
+                style: lane.style.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the top and height of the row for the i'th channel of a
+/// digital area, in pixels at `scale` device pixels per logical pixel.
+fn digital_row(area: &PlotArea, i: usize, scale: usize) -> (usize, usize) {
+    let (y, h) = lane_rect(i, area.channels.len().max(1), area.h * scale);
+    (area.y * scale + y, h)
+}
+
+/// Returns a `Transitions` for each channel drawn as state bars in `lanes`.
+fn lane_transitions(lanes: &[Lane]) -> Vec<Transitions> {
+    let mut chs: Vec<usize> = lanes.iter()
+        .filter(|lane| matches!(lane.style, LaneStyle::States(_)))
+        .flat_map(|lane| lane.channels.iter().copied())
+        .collect();
+    chs.sort_unstable();
+    chs.dedup();
+    chs.into_iter().map(Transitions::new).collect()
+}
+
+/// Draw the data in pixel columns px0 to px1 (see `pixel_map`) into a
+/// patch of `surface` at x, along with the matching section of the
+/// time axis if it is shown.
+fn draw_patch(
+    s: &State, surface: &cairo::Surface,
+    x: usize, px0: i64, px1: i64, view: &View,
+) {
+    let w = (px1 - px0) as usize;
+    let mut areas = plot_areas(s, view);
+    render_patch_background(s, surface, x, w, &areas);
+    let cols = s.config.data_source.borrow().get_colors().unwrap();
+    let scale = s.scale_factor.get() as usize;
+    let h = s.graph_height() as usize;
+
+    let phosphor = s.phosphor.borrow();
+    let mut persistence = s.persistence.borrow_mut();
+    let mut patch_intensity;
+    let intensity = match (&*phosphor, &view.mode) {
+        (None, _) => None,
+        (Some(ph), ViewMode::Triggered) => {
+            // Add each new sweep to the intensity of the earlier ones.
+            let key = PersistenceKey {
+                x, w, scale,
+                zoom_x: view.zoom_x,
+                areas: areas.clone(),
+            };
+            if persistence.as_ref().map(|p| p.key != key).unwrap_or(true) {
+                *persistence = Some(Persistence {
+                    key,
+                    px1: i64::MIN,
+                    intensity: Intensity::new(w * scale, h * scale),
+                });
+            }
+            let p = persistence.as_mut().unwrap();
+            if p.px1 == px1 {
+                // Redrawing the same sweep, don't count it twice.
+                // Only analog areas are accumulated, others are drawn again.
+                for area in areas.iter_mut().filter(|area| area.style.plots_values()) {
+                    area.channels.clear();
+                }
+            } else {
+                p.intensity.decay(ph.decay);
+                p.px1 = px1;
+            }
+            Some((ph, &mut p.intensity))
+        },
+        (Some(ph), _) => {
+            patch_intensity = Intensity::new(w * scale, h * scale);
+            Some((ph, &mut patch_intensity))
+        },
+    };
+    render_patch(surface,
+                 &s.store.borrow(),
+                 &cols, &areas,
+                 w, h,
+                 x, 0 /* y */,
+                 px0, view.zoom_x,
+                 &s.thresholds.borrow(),
+                 s.config.point_style,
+                 intensity,
+                 scale);
+    if s.config.show_time_axis {
+        render_time_axis_patch(s, surface, x, px0, px1, view.zoom_x);
+    }
+}
+
+/// Fill the patch at x, w pixels wide, with the background color and
+/// any gridlines, bands and threshold lines of each area, ready for the
+/// data to be drawn on top.
+fn render_patch_background(
+    s: &State, surface: &cairo::Surface,
+    x: usize, w: usize,
+    areas: &[PlotArea],
+) {
+    let c = cairo::Context::new(surface);
+    c.rectangle(x as f64, 0.0, w as f64, s.graph_height() as f64);
+    c.set_source_rgb(DRAWN_AREA_BACKGROUND_COLOR.0,
+                     DRAWN_AREA_BACKGROUND_COLOR.1,
+                     DRAWN_AREA_BACKGROUND_COLOR.2);
+    c.fill();
+
+    for area in areas {
+        if !area.style.plots_values() {
+            continue;
+        }
+        let (v0, v1, h) = (area.value_min, area.value_max, area.h);
+        let top = area.y as f64;
+
+        if s.config.show_gridlines {
+            set_source_color(&c, s.config.gridline_color);
+            c.set_line_width(1.0);
+            for v in value_ticks(v0, v1, value_min_step(v0, v1, h)) {
+                let y = top + gridline_y(v, v0, v1, h);
+                c.move_to(x as f64, y);
+                c.line_to((x + w) as f64, y);
+            }
+            c.stroke();
+        }
+
+        for band in s.bands.borrow().iter() {
+            if !area.channels.contains(&band.channel) || band.max < v0 || band.min > v1 {
+                continue;
+            }
+            let y0 = top + gridline_y(band.max.min(v1), v0, v1, h).floor();
+            let y1 = top + gridline_y(band.min.max(v0), v0, v1, h).floor() + 1.0;
+            set_source_color_alpha(&c, band.color, BAND_ALPHA);
+            c.rectangle(x as f64, y0, w as f64, y1 - y0);
+            c.fill();
+        }
+        c.set_line_width(1.0);
+        for th in s.thresholds.borrow().iter() {
+            if !area.channels.contains(&th.channel) || th.value < v0 || th.value > v1 {
+                continue;
+            }
+            let y = top + gridline_y(th.value, v0, v1, h);
+            set_source_color(&c, th.color);
+            c.move_to(x as f64, y);
+            c.line_to((x + w) as f64, y);
+            c.stroke();
+        }
+    }
+}
+
+/// Returns the minimum step between value axis ticks for a graph h
+/// pixels high showing values from v0 to v1.
+fn value_min_step(v0: Value, v1: Value, h: usize) -> f64 {
+    VALUE_AXIS_MIN_TICK_SPACING * (v1 - v0) as f64 / h as f64
+}
+
+/// Maps a value to a y pixel in a patch h pixels high showing values
+/// from v0 at the bottom to v1 at the top.
+///
+/// Returns None if the value is outside the patch.
+fn value_to_y(v: Value, v0: Value, v1: Value, h: usize) -> Option<usize> {
+    if v < v0 || v > v1 {
+        return None;
+    }
+    let y = (((v - v0) as f32 / (v1 - v0) as f32) * h as f32) as usize;
+    if y >= h {
+        return None;
+    }
+    // Mirror the y-axis
+    Some(h - y)
+}
+
+/// Returns the value at y pixels from the top of a patch h pixels high
+/// showing values from v0 to v1, clamped to that range.
+fn y_to_value(y: f64, v0: Value, v1: Value, h: usize) -> Value {
+    let frac = ((h as f64 - y) / h as f64).clamp(0.0, 1.0);
+    (v0 as f64 + frac * (v1 - v0) as f64).round() as Value
+}
+
+/// Returns the y coordinate to draw a 1 pixel wide horizontal line at
+/// value v, in a patch h pixels high showing values from v0 to v1.
+fn gridline_y(v: Value, v0: Value, v1: Value, h: usize) -> f64 {
+    // +0.5 to draw the line in the centre of a pixel.
+    value_to_y(v, v0, v1, h).unwrap_or(h).min(h - 1) as f64 + 0.5
+}
+
+fn set_source_color_alpha(c: &cairo::Context, col: Color, alpha: f64) {
+    c.set_source_rgba(col.r() as f64 / 255.0,
+                      col.g() as f64 / 255.0,
+                      col.b() as f64 / 255.0,
+                      alpha);
+}
+
+fn set_axis_font(c: &cairo::Context, config: &Config) {
+    select_axis_font(c, &config.axis_font_family, config.axis_font_size);
+}
+
+/// Draw the section of the time axis under the patch at x, w pixels
+/// wide, showing times from t0 to t1.
+///
+/// Tick positions depend only on time and zoom, so adjacent patches
+/// line up: labels that straddle a patch boundary are drawn clipped in
+/// both patches.
+fn render_time_axis_patch(
+    s: &State, surface: &cairo::Surface,
+    x: usize, px0: i64, px1: i64, zoom_x: f64,
+) {
+    trace!("render_time_axis_patch x={} px0={} px1={}", x, px0, px1);
+    let w = px1 - px0;
+    let (t0, t1) = (pixel_to_t(px0, zoom_x), pixel_to_t(px1, zoom_x));
+    let y0 = s.graph_height() as f64;
+    let c = cairo::Context::new(surface);
+    c.rectangle(x as f64, y0, w as f64, TIME_AXIS_HEIGHT as f64);
+    c.clip();
+    set_source_color(&c, s.config.axis_background_color);
+    c.paint();
+
+    set_source_color(&c, s.config.axis_color);
+    c.set_line_width(1.0);
+    set_axis_font(&c, &s.config);
+
+    let min_step_t = TIME_AXIS_MIN_TICK_SPACING * zoom_x;
+    let ticks = TimeTicks::new(s.config.time_axis_labels,
+                               s.config.wall_clock.as_ref(),
+                               s.store.borrow().origin_t(),
+                               min_step_t);
+    // Include ticks just outside the patch whose labels overlap it.
+    let margin = min_step_t as Time;
+    for (t, label) in ticks.ticks(t0.saturating_sub(margin), t1 + margin) {
+        // +0.5 to draw the 1 pixel wide line in the centre of a pixel.
+        let tx = x as f64 + (t_to_pixel(t, zoom_x) - px0) as f64 + 0.5;
+        c.move_to(tx, y0);
+        c.line_to(tx, y0 + AXIS_TICK_LENGTH);
+        c.stroke();
+
+        let extents = c.text_extents(&label);
+        c.move_to(tx - extents.width / 2.0 - extents.x_bearing,
+                  y0 + TIME_AXIS_HEIGHT as f64 - 4.0);
+        c.show_text(&label);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn render_patch(
     surface: &cairo::Surface,
-    store: &Store, cols: &[Color],
+    store: &Store, cols: &[Color], areas: &[PlotArea],
     pw: usize, ph: usize,
     x: usize, y: usize,
-    t0: Time, t1: Time, v0: Value, v1: Value,
+    px0: i64, zoom_x: f64,
+    thresholds: &[Threshold],
     point_style: PointStyle,
+    mut phosphor: Option<(&Phosphor, &mut Intensity)>,
+    scale: usize,
 ) {
-    trace!("render_patch: pw={}, ph={} x={} y={}", pw, ph, x, y);
-    let mut patch_bytes = vec![0u8; pw * ph * BYTES_PER_PIXEL];
-    render_patch_to_bytes(store, cols, &mut patch_bytes,
-                          pw, ph,
-                          t0, t1,
-                          v0, v1,
+    trace!("render_patch: pw={}, ph={} x={} y={} scale={}", pw, ph, x, y, scale);
+    // Rasterise at device resolution, so the patch is not scaled up on
+    // HiDPI displays.
+    let (pbw, pbh) = (pw * scale, ph * scale);
+    let mut patch_bytes = vec![0u8; pbw * pbh * BYTES_PER_PIXEL];
+    render_patch_to_bytes(store, cols, areas, &mut patch_bytes,
+                          phosphor.as_mut().map(|(_, i)| &mut **i),
+                          pbw, pbh,
+                          px0, zoom_x, scale,
+                          thresholds,
                           point_func_select(point_style)
                           ).unwrap();
+    if let Some((settings, intensity)) = phosphor {
+        intensity.to_bytes(&mut patch_bytes, settings);
+    }
     copy_patch(surface, patch_bytes,
                pw, ph,
-               x, y);
+               x, y,
+               scale);
 }
 
-fn point_func_select(s: PointStyle) -> &'static dyn Fn(usize, usize, usize, usize, &mut [u8], Color) {
+fn point_func_select(s: PointStyle) -> &'static PointFunc {
     match s {
         PointStyle::Point => &point_func_point,
         PointStyle::Cross => &point_func_cross,
     }
 }
 
-fn point_func_point(x: usize, y: usize, pbw: usize, pbh: usize, pb: &mut [u8], col: Color) {
-    if x < pbw && y < pbh {
-        let i = BYTES_PER_PIXEL * (pbw * y + x);
-        pb[i+2] = col.0; // R
-        pb[i+1] = col.1; // G
-        pb[i+0] = col.2; // B
-        pb[i+3] = 255;   // A
-    }
+fn point_func_point(x: usize, y: usize, pixel: &mut dyn FnMut(usize, usize)) {
+    pixel(x, y);
 }
 
-fn point_func_cross(x: usize, y: usize, pbw: usize, pbh: usize, pb: &mut [u8], col: Color) {
-    let mut pixel = |px: usize, py: usize| {
-        if px < pbw && py < pbh {
-            let i = BYTES_PER_PIXEL * (pbw * py + px);
-            pb[i+2] = col.0; // R
-            pb[i+1] = col.1; // G
-            pb[i+0] = col.2; // B
-            pb[i+3] = 255;   // A
-        }
-    };
-
+fn point_func_cross(x: usize, y: usize, pixel: &mut dyn FnMut(usize, usize)) {
     pixel(x+1, y+1);
     if y >= 1 {
         pixel(x+1, y-1);
@@ -619,72 +2996,243 @@ fn point_func_cross(x: usize, y: usize, pbw: usize, pbh: usize, pb: &mut [u8], c
     }
 }
 
+fn set_pixel(pb: &mut [u8], pbw: usize, pbh: usize, x: usize, y: usize, col: Color) {
+    if x < pbw && y < pbh {
+        let i = BYTES_PER_PIXEL * (pbw * y + x);
+        pb[i+2] = col.0; // R
+        pb[i+1] = col.1; // G
+        pb[i]   = col.2; // B
+        pb[i+3] = 255;   // A
+    }
+}
+
+/// Draw points into the patch buffer `pb`, or if `intensity` is set add
+/// them as hits there instead. Digital areas are always drawn into `pb`.
+#[allow(clippy::too_many_arguments)]
 fn render_patch_to_bytes(
-    store: &Store, cols: &[Color],
-    pb: &mut [u8], pbw: usize, pbh: usize,
-    t0: Time, t1: Time, v0: Value, v1: Value,
-    point_func: &dyn Fn(usize, usize, usize, usize, &mut [u8], Color),
+    store: &Store, cols: &[Color], areas: &[PlotArea],
+    pb: &mut [u8], mut intensity: Option<&mut Intensity>, pbw: usize, pbh: usize,
+    px0: i64, zoom_x: f64, scale: usize,
+    thresholds: &[Threshold],
+    point_func: &PointFunc,
 ) -> Result<()>
 {
     trace!("render_patch_to_bytes: pbw={}", pbw);
     assert!(pbw >= 1);
 
+    // The patch shows pixel columns px0 up to px1, each `scale` device
+    // pixels wide.
+    let px1 = px0 + (pbw / scale) as i64;
+    let (t0, t1) = (pixel_to_t(px0, zoom_x), pixel_to_t(px1, zoom_x));
     let points = store.query_range(t0, t1)?;
-    for p in points {
-        assert!(p.t >= t0 && p.t <= t1);
-
-        let x = (((p.t-t0) as f32 / (t1-t0) as f32) * pbw as f32) as usize;
-        if !(x < pbw) {
+    let mut xs = Vec::with_capacity(points.len());
+    for p in points.iter() {
+        assert!(p.t >= t0 && p.t < t1);
+
+        // Find the column exactly, then the device pixel within it.
+        let px = t_to_pixel(p.t, zoom_x);
+        let sub = ((p.t as f64 / zoom_x - px as f64) * scale as f64)
+            .floor().max(0.0).min((scale - 1) as f64) as usize;
+        let x = (px - px0) as usize * scale + sub;
+        if x >= pbw {
             // Should be guaranteed by store.query.
             panic!("x < pbw: x={} pbw={}", x, pbw);
         }
+        xs.push(x);
+    }
 
-        for ch in 0..store.val_len() {
-            let col = cols[ch as usize % cols.len()];
-            let y = (((p.vals()[ch as usize]-v0) as f32 / (v1-v0) as f32) * pbh as f32) as usize;
-            if y >= pbh {
-                // Skip points that are outside our render patch.
+    for area in areas {
+        match area.style {
+            LaneStyle::Analog => (),
+            LaneStyle::Spectrogram(ref sg) => {
+                let before = store.query_before(t0)?;
+                render_spectrogram_to_bytes(&points, &xs, before.as_ref(), area, sg,
+                                            scale, pb, pbw, pbh);
                 continue;
-            }
-            // Mirror the y-axis
-            let y = pbh - y;
+            },
+            LaneStyle::Logic | LaneStyle::States(_) => {
+                // The value before the patch, to draw up to the first point.
+                let before = store.query_before(t0)?;
+                for (i, &ch) in area.channels.iter().enumerate() {
+                    let row = digital_row(area, i, scale);
+                    let prev = before.as_ref().map(|p| p.vals()[ch]);
+                    render_digital_to_bytes(&points, &xs, ch, prev, &area.style,
+                                            cols[ch % cols.len()], row, pb, pbw, pbh);
+                }
+                continue;
+            },
+        }
+
+        // The area's rows in device pixels. Points are clipped to them.
+        let (ay0, ah) = (area.y * scale, area.h * scale);
+        let ay1 = (ay0 + ah).min(pbh);
+        for (p, &x) in points.iter().zip(xs.iter()) {
+            for &ch in area.channels.iter() {
+                let col = highlight_color(thresholds, ch, p.vals()[ch])
+                    .unwrap_or(cols[ch % cols.len()]);
+                let y = match value_to_y(p.vals()[ch], area.value_min, area.value_max, ah) {
+                    Some(y) => ay0 + y,
+                    // Skip points that are outside our render patch.
+                    None => continue,
+                };
 
-            point_func(x, y, pbw, pbh, pb, col);
+                match intensity {
+                    Some(ref mut i) => point_func(x, y, &mut |px, py| {
+                        if py >= ay0 && py < ay1 {
+                            i.hit(px, py);
+                        }
+                    }),
+                    None => point_func(x, y, &mut |px, py| {
+                        if py >= ay0 && py < ay1 {
+                            set_pixel(pb, pbw, pbh, px, py, col);
+                        }
+                    }),
+                }
+            }
         }
     }
 
     Ok(())
 }
 
+/// Draw `area` as a spectrogram: each point as a strip from its column
+/// up to the next point's, with a row for each channel from the bottom
+/// up. `xs` are the columns of `points`, and `before` is the last point
+/// before the patch.
+#[allow(clippy::too_many_arguments)]
+fn render_spectrogram_to_bytes(
+    points: &[Point], xs: &[usize], before: Option<&Point>,
+    area: &PlotArea, sg: &Spectrogram, scale: usize,
+    pb: &mut [u8], pbw: usize, pbh: usize,
+) {
+    // Bins are by channel index, whatever the draw order.
+    let mut bins = area.channels.clone();
+    bins.sort_unstable();
+    let n = bins.len();
+    if n == 0 {
+        return;
+    }
+    let (ay0, ah) = (area.y * scale, area.h * scale);
+
+    let strip = |pb: &mut [u8], x0: usize, x1: usize, p: &Point| {
+        for (i, &ch) in bins.iter().enumerate() {
+            let col = sg.color(p.vals()[ch], area.value_min, area.value_max);
+            let (y, h) = lane_rect(n - 1 - i, n, ah);
+            for x in x0..x1 {
+                for y in ay0 + y..ay0 + y + h {
+                    set_pixel(pb, pbw, pbh, x, y, col);
+                }
+            }
+        }
+    };
+
+    let mut cur = before;
+    let mut x_from = 0;
+    for (p, &x) in points.iter().zip(xs.iter()) {
+        if let Some(c) = cur {
+            strip(pb, x_from, x, c);
+        }
+        x_from = x;
+        cur = Some(p);
+    }
+    if let Some(c) = cur {
+        strip(pb, x_from, pbw, c);
+    }
+}
+
+/// Draw channel `ch` as a logic trace or state bar in the device pixel
+/// rows `row` (top, height) of the patch buffer. `xs` are the columns
+/// of `points`, and `prev` is the channel's value before the patch.
+#[allow(clippy::too_many_arguments)]
+fn render_digital_to_bytes(
+    points: &[Point], xs: &[usize], ch: usize, prev: Option<Value>,
+    style: &LaneStyle, col: Color, row: (usize, usize),
+    pb: &mut [u8], pbw: usize, pbh: usize,
+) {
+    let (row_y, row_h) = row;
+    if row_h < 3 {
+        return;
+    }
+    let (high_y, low_y) = (row_y + row_h / 4, row_y + row_h * 3 / 4);
+    let (bar_y0, bar_y1) = (row_y + 1, row_y + row_h - 1);
+    let level_y = |v: Value| if v != 0 { high_y } else { low_y };
+
+    // Draw the value v over columns x0 up to x1.
+    let run = |pb: &mut [u8], x0: usize, x1: usize, v: Value| {
+        for x in x0..x1 {
+            match style {
+                LaneStyle::States(_) => {
+                    let fill = STATE_COLORS[v as usize % STATE_COLORS.len()];
+                    for y in bar_y0 + 1..bar_y1 - 1 {
+                        set_pixel(pb, pbw, pbh, x, y, fill);
+                    }
+                    set_pixel(pb, pbw, pbh, x, bar_y0, col);
+                    set_pixel(pb, pbw, pbh, x, bar_y1 - 1, col);
+                },
+                _ => set_pixel(pb, pbw, pbh, x, level_y(v), col),
+            }
+        }
+    };
+
+    let mut cur = prev;
+    let mut x_from = 0;
+    for (p, &x) in points.iter().zip(xs.iter()) {
+        let v = p.vals()[ch];
+        if let Some(c) = cur {
+            run(pb, x_from, x, c);
+            x_from = x;
+            if c != v {
+                // Draw the transition in column x.
+                let (y0, y1) = match style {
+                    LaneStyle::States(_) => (bar_y0, bar_y1),
+                    _ => (high_y, low_y + 1),
+                };
+                if style != &LaneStyle::Logic || level_y(c) != level_y(v) {
+                    for y in y0..y1 {
+                        set_pixel(pb, pbw, pbh, x, y, col);
+                    }
+                    x_from = x + 1;
+                }
+            }
+        } else {
+            x_from = x;
+        }
+        cur = Some(v);
+    }
+    if let Some(c) = cur {
+        run(pb, x_from, pbw, c);
+    }
+}
+
+/// Copy a patch rendered at `scale` device pixels per logical pixel
+/// to `backing_surface`. `w`, `h`, `x`, and `y` are in logical pixels.
 fn copy_patch(
     backing_surface: &cairo::Surface,
     bytes: Vec<u8>,
     w: usize, h: usize,
-    x: usize, y: usize
+    x: usize, y: usize,
+    scale: usize,
 ) {
 
-    trace!("copy_patch w={} x={}", w, x);
+    trace!("copy_patch w={} x={} scale={}", w, x, scale);
 
     // Create an ImageSurface from our bytes
     let patch_surface = cairo::ImageSurface::create_for_data(
         bytes,
         cairo::Format::ARgb32,
-        w as i32,
-        h as i32,
-        (w * BYTES_PER_PIXEL) as i32 /* stride */
+        (w * scale) as i32,
+        (h * scale) as i32,
+        (w * scale * BYTES_PER_PIXEL) as i32 /* stride */
     ).unwrap();
+    patch_surface.set_device_scale(scale as f64, scale as f64);
 
-    // Copy from the ImageSurface to backing_surface
-    let c = cairo::Context::new(&backing_surface);
-    // Fill target area with background colour.
+    // Copy from the ImageSurface to backing_surface, over the
+    // background already drawn there.
+    let c = cairo::Context::new(backing_surface);
     c.rectangle(x as f64,
                 y as f64,
                 w as f64, // width
                 h as f64  /* height */);
-    c.set_source_rgb(DRAWN_AREA_BACKGROUND_COLOR.0,
-                     DRAWN_AREA_BACKGROUND_COLOR.1,
-                     DRAWN_AREA_BACKGROUND_COLOR.2);
-    c.fill_preserve();
     // Fill target area with patch data.
     c.set_source_surface(&patch_surface,
                          x as f64,
@@ -692,13 +3240,15 @@ fn copy_patch(
     c.fill();
 }
 
-fn create_backing_surface(win: &gdk::Window, w: u32, h: u32) -> cairo::Surface {
+/// Create a surface w by h logical pixels, with `scale` device pixels per
+/// logical pixel.
+fn create_backing_surface(win: &gdk::Window, w: u32, h: u32, scale: i32) -> cairo::Surface {
     let surface =
         win.create_similar_image_surface(
             cairo::Format::Rgb24.into(),
             w as i32 /* width */,
             h as i32 /* height */,
-            1 /* scale */).unwrap();
+            scale /* scale */).unwrap();
     {
         // Clear backing_surface
         let c = cairo::Context::new(&surface);
@@ -710,3 +3260,66 @@ fn create_backing_surface(win: &gdk::Window, w: u32, h: u32) -> cairo::Surface {
     }
     surface
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Cursors, Drag, DragAction, DragEnd, View, ViewMode};
+    use crate::store::Store;
+    use crate::test_util::points;
+
+    fn drag(button: u32, action: DragAction, moved: bool) -> Drag {
+        Drag {
+            button,
+            action,
+            start_x: 0.0,
+            start_y: 0.0,
+            start_view: View {
+                zoom_x: 1.0,
+                last_drawn_t: 0,
+                last_drawn_x: 0,
+                min_t: 0,
+                max_t: 0,
+                mode: ViewMode::Following,
+                value_min: 0,
+                value_max: 100,
+            },
+            x: 0.0,
+            y: 0.0,
+            moved,
+        }
+    }
+
+    #[test]
+    fn drag_end() {
+        // A release ends a drag in exactly one way, so e.g. a select
+        // drag raises selection_signal once.
+        assert_eq!(drag(1, DragAction::Select, true).end(), DragEnd::Select);
+        assert_eq!(drag(1, DragAction::Select, false).end(), DragEnd::Click);
+        assert_eq!(drag(1, DragAction::BoxZoom, true).end(), DragEnd::BoxZoom);
+        assert_eq!(drag(1, DragAction::Pan, true).end(), DragEnd::Nothing);
+        assert_eq!(drag(2, DragAction::Pan, false).end(), DragEnd::Nothing);
+    }
+
+    #[test]
+    fn cursors_follow_data() {
+        let mut store = Store::new(1);
+        let cursors = Cursors::new(&store, None, Some(20), Some(40));
+        assert!(cursors.a_values.is_empty());
+        assert!(cursors.delta_values.is_empty());
+
+        // Placed before the data arrived.
+        store.ingest(&points(10, 0, &[10, 20, 30, 40])).unwrap();
+        let cursors = cursors.refresh(&store, None);
+        assert_eq!(cursors.a_values, vec![20]);
+        assert_eq!(cursors.b_values, vec![40]);
+        assert_eq!(cursors.delta_values, vec![20]);
+        assert_eq!(cursors.delta_t, Some(20));
+
+        // The data at cursor A has been discarded.
+        store.discard(0, 30).unwrap();
+        let cursors = cursors.refresh(&store, None);
+        assert!(cursors.a_values.is_empty());
+        assert_eq!(cursors.b_values, vec![40]);
+        assert!(cursors.delta_values.is_empty());
+    }
+}
@@ -1,7 +1,17 @@
-use crate::{Config, Graph, View, ViewMode};
+use crate::{Color, Config, Cursors, Graph, histogram::Histogram, Time, Value, View, ViewMode};
 use gdk::prelude::*;
 use gtk::prelude::*;
-use std::{rc::Rc, cell::RefCell};
+use std::{rc::Rc, cell::{Cell, RefCell}};
+
+/// The factor the zoom buttons change the zoom level by.
+const ZOOM_BUTTON_FACTOR: f64 = 2.0;
+
+/// Width of the histogram panel in pixels, see `ConfigBuilder::show_histogram()`.
+const HISTOGRAM_WIDTH: i32 = 80;
+/// Height of each histogram bin in pixels.
+const HISTOGRAM_BIN_HEIGHT: u32 = 4;
+/// Opacity of the histogram bars, so overlapping channels show through.
+const HISTOGRAM_ALPHA: f64 = 0.6;
 
 /// A GTK widget that contains a graph and controls to navigate it.
 ///
@@ -11,6 +21,15 @@ pub struct GraphWithControls {
     s: Rc<State>,
 }
 
+/// Cloning a `GraphWithControls` returns another handle to the same widget.
+impl Clone for GraphWithControls {
+    fn clone(&self) -> GraphWithControls {
+        GraphWithControls {
+            s: self.s.clone()
+        }
+    }
+}
+
 struct State {
     controls_box: gtk::Box,
 
@@ -18,10 +37,25 @@ struct State {
     btn_zoom_x_out: gtk::Button,
     btn_zoom_x_in: gtk::Button,
     btn_follow: gtk::Button,
+    btn_zoom_back: gtk::Button,
+    btn_pause: gtk::ToggleButton,
+    btn_clear_cursors: gtk::Button,
+    cursor_readout: gtk::Label,
+    histogram: Option<HistogramPanel>,
+
+    /// Whether the graph was paused when the controls were last updated.
+    paused: Cell<bool>,
 
     graph: RefCell<Graph>,
 }
 
+/// The histogram beside the graph.
+struct HistogramPanel {
+    drawing_area: gtk::DrawingArea,
+    histogram: RefCell<Histogram>,
+    colors: Vec<Color>,
+}
+
 impl GraphWithControls {
     /// Build and show a `GraphWithControls` widget in the target `gtk::Container`.
     pub fn build_ui<C>(config: Config, container: &C, gdk_window: &gdk::Window
@@ -36,13 +70,38 @@ impl GraphWithControls {
             .build();
         container.add(&controls_box);
 
-        let graph = Graph::build_ui(config, &controls_box, gdk_window);
+        // The graph and the histogram panel, side by side.
+        let graph_box = gtk::BoxBuilder::new()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(0)
+            .build();
+        controls_box.add(&graph_box);
+
+        let show_histogram = config.show_histogram();
+        let graph = Graph::build_ui(config, &graph_box, gdk_window);
+
+        let histogram = if show_histogram {
+            let drawing_area = gtk::DrawingAreaBuilder::new()
+                .width_request(HISTOGRAM_WIDTH)
+                .build();
+            graph_box.add(&drawing_area);
+            let view = graph.view();
+            Some(HistogramPanel {
+                drawing_area,
+                histogram: RefCell::new(Histogram::new(
+                    graph.channel_names().len(), 0, view.value_min, view.value_max)),
+                colors: graph.colors(),
+            })
+        } else {
+            None
+        };
 
+        // The scrollbar fills the width of controls_box, so it stays
+        // the same width as the graph when the graph is resized.
         let scrollbar = gtk::ScrollbarBuilder::new()
             .orientation(gtk::Orientation::Horizontal)
-            .halign(gtk::Align::Start)
             .build();
-        scrollbar.set_property_width_request(graph.width() as i32);
+        scrollbar.set_margin_start(graph.graph_x_offset() as i32);
         controls_box.add(&scrollbar);
 
         let buttons_box = gtk::BoxBuilder::new()
@@ -56,6 +115,11 @@ impl GraphWithControls {
             .build();
         buttons_box.add(&btn_follow);
 
+        let btn_pause = gtk::ToggleButtonBuilder::new()
+            .label("Pause")
+            .build();
+        buttons_box.add(&btn_pause);
+
         let btn_zoom_x_in = gtk::ButtonBuilder::new()
             .label("Zoom X in")
             .build();
@@ -67,6 +131,27 @@ impl GraphWithControls {
             .build();
         buttons_box.add(&btn_zoom_x_out);
 
+        let btn_zoom_back = gtk::ButtonBuilder::new()
+            .label("Zoom back")
+            .sensitive(false)
+            .build();
+        buttons_box.add(&btn_zoom_back);
+
+        let btn_clear_cursors = gtk::ButtonBuilder::new()
+            .label("Clear cursors")
+            .sensitive(false)
+            .build();
+        buttons_box.add(&btn_clear_cursors);
+
+        // Shows the measurement cursors, hidden until one is placed.
+        let cursor_readout = gtk::LabelBuilder::new()
+            .halign(gtk::Align::Start)
+            .selectable(true)
+            .margin_start(graph.graph_x_offset() as i32)
+            .build();
+        cursor_readout.set_no_show_all(true);
+        controls_box.add(&cursor_readout);
+
         // Set up the state
 
         let s = Rc::new(State {
@@ -76,6 +161,13 @@ impl GraphWithControls {
             btn_zoom_x_out: btn_zoom_x_out.clone(),
             btn_zoom_x_in: btn_zoom_x_in.clone(),
             btn_follow: btn_follow.clone(),
+            btn_zoom_back: btn_zoom_back.clone(),
+            btn_pause: btn_pause.clone(),
+            btn_clear_cursors: btn_clear_cursors.clone(),
+            cursor_readout: cursor_readout.clone(),
+            histogram,
+
+            paused: Cell::new(false),
 
             graph: RefCell::new(graph),
         });
@@ -84,6 +176,7 @@ impl GraphWithControls {
         };
 
         update_controls(&g, &g.s.graph.borrow().view());
+        update_histogram(&g, &g.s.graph.borrow().view(), g.s.graph.borrow().selection());
 
         // Event handlers that require state.
 
@@ -98,30 +191,81 @@ impl GraphWithControls {
             gc.s.graph.borrow().set_follow()
         });
 
+        let gc = g.clone();
+        btn_pause.connect_toggled(move |btn| {
+            // Ignore changes made by update_controls.
+            if btn.get_active() == gc.s.paused.get() {
+                return;
+            }
+            if btn.get_active() {
+                gc.s.graph.borrow().pause();
+            } else {
+                gc.s.graph.borrow().resume();
+            }
+        });
+
+        let gc = g.clone();
+        btn_clear_cursors.connect_clicked(move |_btn| {
+            gc.s.graph.borrow().clear_cursors();
+        });
+
         let gc = g.clone();
         btn_zoom_x_in.connect_clicked(move |_btn| {
-            let new = gc.s.graph.borrow().view().zoom_x / 2.0;
-            gc.s.graph.borrow().set_zoom_x(new);
+            let new = gc.s.graph.borrow().target_zoom_x() / ZOOM_BUTTON_FACTOR;
+            gc.s.graph.borrow().animate_zoom_x(new, None);
         });
 
         let gc = g.clone();
         btn_zoom_x_out.connect_clicked(move |_btn| {
-            let new = gc.s.graph.borrow().view().zoom_x * 2.0;
-            gc.s.graph.borrow().set_zoom_x(new);
+            let new = gc.s.graph.borrow().target_zoom_x() * ZOOM_BUTTON_FACTOR;
+            gc.s.graph.borrow().animate_zoom_x(new, None);
+        });
+
+        let gc = g.clone();
+        btn_zoom_back.connect_clicked(move |_btn| {
+            gc.s.graph.borrow().zoom_back();
         });
 
+        {
+            // Scope the borrow on cursors_observable.
+            let gc = g.clone();
+            s.graph.borrow_mut().cursors_observable().connect(move |cursors| {
+                update_cursor_readout(&gc, cursors);
+            });
+        }
+
         {
             // Scope the borrow on view_observable.
             let gc = g.clone();
             s.graph.borrow_mut().view_observable().connect(move |view| {
-                update_controls(&gc, &view);
+                update_controls(&gc, view);
+                let selection = gc.s.graph.borrow().selection();
+                update_histogram(&gc, view, selection);
+            });
+        }
+
+        if let Some(panel) = &s.histogram {
+            let gc = g.clone();
+            panel.drawing_area.connect_draw(move |_ctrl, ctx| {
+                histogram_draw(ctx, &gc)
+            });
+
+            let gc = g.clone();
+            s.graph.borrow_mut().selection_observable().connect(move |selection| {
+                let view = gc.s.graph.borrow().view();
+                update_histogram(&gc, &view, *selection);
+            });
+
+            // Redraw when channels are shown or hidden.
+            let da = panel.drawing_area.clone();
+            s.graph.borrow_mut().channels_observable().connect(move |_channels| {
+                da.queue_draw();
             });
         }
 
         let gc = g.clone();
-        g.s.graph.borrow().drawing_area().add_events(gdk::EventMask::BUTTON_PRESS_MASK);
-        g.s.graph.borrow().drawing_area().connect_button_press_event(move |_ctrl, ev| {
-            drawing_area_button_press(&gc, ev)
+        s.graph.borrow_mut().click_signal().connect(move |(x, y)| {
+            graph_click(&gc, x, y);
         });
 
         // Show everything recursively
@@ -130,12 +274,6 @@ impl GraphWithControls {
         g
     }
 
-    fn clone(&self) -> GraphWithControls {
-        GraphWithControls {
-            s: self.s.clone()
-        }
-    }
-
     /// Show the graph and controls.
     pub fn show(&self) {
         self.s.controls_box.show();
@@ -165,31 +303,36 @@ fn update_controls(g: &GraphWithControls, view: &View) {
     match view.mode {
         ViewMode::Following =>
             adj.set_value(s.graph.borrow().last_t() as f64),
-        ViewMode::Scrolled => adj.set_value(view.last_drawn_t as f64 -
-                                            ((s.graph.borrow().width() as f64) * view.zoom_x)),
+        ViewMode::Scrolled | ViewMode::Paused | ViewMode::Triggered =>
+            adj.set_value(view.last_drawn_t as f64 -
+                          ((s.graph.borrow().width() as f64) * view.zoom_x)),
     }
 
     s.btn_zoom_x_in.set_sensitive(view.zoom_x > s.graph.borrow().max_zoom_x());
     s.btn_zoom_x_out.set_sensitive(view.zoom_x < s.graph.borrow().base_zoom_x());
-    s.btn_follow.set_sensitive(view.mode == ViewMode::Scrolled);
+    s.btn_follow.set_sensitive(view.mode != ViewMode::Following);
+    s.paused.set(view.mode == ViewMode::Paused);
+    s.btn_pause.set_active(view.mode == ViewMode::Paused);
+    s.btn_zoom_back.set_sensitive(s.graph.borrow().can_zoom_back());
 }
 
-fn drawing_area_button_press(g: &GraphWithControls, ev: &gdk::EventButton) -> Inhibit {
-    let pos = ev.get_position();
-    let pt = g.s.graph.borrow().drawing_area_pos_to_point(pos.0, pos.1);
-    debug!("drawing_area button_press pos={:?} pt={:?}", pos, pt);
+/// Show the point under a click on the graph in an info bar.
+fn graph_click(g: &GraphWithControls, x: f64, y: f64) {
+    let graph = g.s.graph.borrow();
+    let pt = graph.drawing_area_pos_to_point(x, y);
+    debug!("graph_click pos={:?} pt={:?}", (x, y), pt);
 
     if let Some(pta) = pt {
         let info_bar = gtk::InfoBarBuilder::new()
             .halign(gtk::Align::Start)
             .build();
         g.s.controls_box.add(&info_bar);
-        info_bar.set_property_width_request(g.s.graph.borrow().width() as i32);
+        info_bar.set_property_width_request(graph.width() as i32);
 
         info_bar.get_content_area().add(&gtk::Label::new(Some("Time, [Values]:")));
 
         let entry = gtk::EntryBuilder::new()
-            .text(&*format!("{}, {:?}", pta.t, pta.vals()))
+            .text(&format!("{}, {:?}", graph.format_time(pta.t), pta.vals()))
             .editable(false)
             .hexpand(true)
             .build();
@@ -208,6 +351,119 @@ fn drawing_area_button_press(g: &GraphWithControls, ev: &gdk::EventButton) -> In
 
         info_bar.show_all();
     }
+}
+
+/// Count the data the histogram panel shows, if any: the selection, or
+/// otherwise what `view` shows.
+fn update_histogram(g: &GraphWithControls, view: &View, selection: Option<(Time, Time)>) {
+    let s = &g.s;
+    let panel = match &s.histogram {
+        Some(panel) => panel,
+        None => return,
+    };
+    let graph = s.graph.borrow();
+    let (t0, t1) = selection.unwrap_or_else(|| {
+        let window_t = (view.last_drawn_x as f64 * view.zoom_x) as Time;
+        (view.last_drawn_t.saturating_sub(window_t), view.last_drawn_t)
+    });
+
+    let mut histogram = panel.histogram.borrow_mut();
+    let bins = (graph.height() / HISTOGRAM_BIN_HEIGHT) as usize;
+    if !histogram.has_bins(bins, view.value_min, view.value_max) {
+        *histogram = Histogram::new(graph.channel_names().len(), bins,
+                                    view.value_min, view.value_max);
+    }
+    histogram.update(t0, t1.saturating_add(1), graph.first_t(),
+                     |t0, t1| graph.points_in(t0, t1));
+    panel.drawing_area.queue_draw();
+}
+
+/// Handle the histogram panel's draw signal.
+fn histogram_draw(ctx: &cairo::Context, g: &GraphWithControls) -> Inhibit {
+    let s = &g.s;
+    let panel = s.histogram.as_ref().unwrap();
+    let graph = s.graph.borrow();
+    let w = panel.drawing_area.get_allocated_width() as f64;
+    let h = graph.height() as f64;
+
+    ctx.set_source_rgb(0.0, 0.0, 0.0);
+    ctx.rectangle(0.0, 0.0, w, h);
+    ctx.fill();
+
+    // Lanes and spectrograms don't share a single value scale.
+    if !graph.lanes().is_empty() || graph.spectrogram().is_some() {
+        return Inhibit(false);
+    }
+
+    let histogram = panel.histogram.borrow();
+    let channels = graph.channels();
+    let visible: Vec<usize> = channels.draw_order.iter().cloned()
+        .filter(|ch| channels.visible[*ch])
+        .collect();
+    let max = visible.iter()
+        .flat_map(|ch| histogram.counts(*ch).iter())
+        .cloned()
+        .max()
+        .unwrap_or(0);
+    if max == 0 {
+        return Inhibit(false);
+    }
+
+    let bin_h = h / histogram.bins() as f64;
+    for ch in visible {
+        let col = panel.colors[ch % panel.colors.len()];
+        ctx.set_source_rgba(col.r() as f64 / 255.0,
+                            col.g() as f64 / 255.0,
+                            col.b() as f64 / 255.0,
+                            HISTOGRAM_ALPHA);
+        for (i, n) in histogram.counts(ch).iter().enumerate() {
+            if *n == 0 {
+                continue;
+            }
+            // Bin 0 is at the bottom, like the graph's value_min.
+            let y = h - (i + 1) as f64 * bin_h;
+            ctx.rectangle(0.0, y, *n as f64 / max as f64 * w, bin_h);
+        }
+        ctx.fill();
+    }
 
     Inhibit(false)
 }
+
+/// Update the measurement cursor readout from the latest `Cursors`.
+fn update_cursor_readout(g: &GraphWithControls, cursors: &Cursors) {
+    let s = &g.s;
+    let placed = cursors.a.is_some() || cursors.b.is_some();
+    s.btn_clear_cursors.set_sensitive(placed);
+    if !placed {
+        s.cursor_readout.hide();
+        return;
+    }
+
+    let graph = s.graph.borrow();
+    let time = |t: Option<Time>| t.map_or("-".to_owned(), |t| graph.format_time(t));
+    let mut text = format!("A: {}    B: {}", time(cursors.a), time(cursors.b));
+    match (cursors.delta_t, cursors.delta_secs) {
+        (_, Some(secs)) => text.push_str(&format!("    Δt: {:.6} s", secs)),
+        (Some(dt), None) => text.push_str(&format!("    Δt: {}", dt)),
+        (None, None) => (),
+    }
+    if let Some(f) = cursors.frequency {
+        let unit = if cursors.delta_secs.is_some() { " Hz" } else { "" };
+        text.push_str(&format!("    1/Δt: {:.6}{}", f, unit));
+    }
+    for (ch, name) in graph.channel_names().iter().enumerate() {
+        let value = |vs: &[Value]| vs.get(ch).map_or("-".to_owned(), |v| v.to_string());
+        text.push_str(&format!("\n{}: A {}    B {}", name,
+                               value(&cursors.a_values), value(&cursors.b_values)));
+        if let Some(dv) = cursors.delta_values.get(ch) {
+            text.push_str(&format!("    Δ {:+}", dv));
+        }
+        let unit = &graph.channel_units()[ch];
+        if !unit.is_empty() {
+            text.push_str(&format!(" {}", unit));
+        }
+    }
+    s.cursor_readout.set_text(&text);
+    s.cursor_readout.show();
+}
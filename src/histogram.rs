@@ -0,0 +1,146 @@
+//! Counting the distribution of each channel's values over a range of
+//! time, for the histogram panel of `GraphWithControls`.
+
+use crate::{Point, Time, Value};
+
+/// Counts of each channel's values in bins, over the points with
+/// times from `t0` up to `t1`.
+pub(crate) struct Histogram {
+    t0: Time,
+    t1: Time,
+    v0: Value,
+    v1: Value,
+    /// Counts by channel, then by bin from `v0` up.
+    counts: Vec<Vec<u32>>,
+}
+
+impl Histogram {
+    /// Create an empty histogram with `bins` bins evenly dividing the
+    /// values from v0 to v1.
+    pub(crate) fn new(num_channels: usize, bins: usize, v0: Value, v1: Value) -> Histogram {
+        Histogram {
+            t0: 0,
+            t1: 0,
+            v0,
+            v1,
+            counts: vec![vec![0; bins.max(1)]; num_channels],
+        }
+    }
+
+    /// Returns true if this histogram has `bins` bins over the values v0 to v1.
+    pub(crate) fn has_bins(&self, bins: usize, v0: Value, v1: Value) -> bool {
+        self.bins() == bins.max(1) && self.v0 == v0 && self.v1 == v1
+    }
+
+    pub(crate) fn bins(&self) -> usize {
+        self.counts.first().map_or(1, |c| c.len())
+    }
+
+    /// Returns the counts of channel `ch` by bin, from v0 up.
+    pub(crate) fn counts(&self, ch: usize) -> &[u32] {
+        &self.counts[ch]
+    }
+
+    /// Count the points with times from t0 up to t1 instead. Only the
+    /// points that enter or leave the range are queried, with `query`,
+    /// unless the new range doesn't overlap the old one.
+    ///
+    /// `first_t` is the oldest time still stored. Points counted before
+    /// it have been discarded and can't be queried to remove them, so
+    /// then everything is counted again.
+    pub(crate) fn update<F>(&mut self, t0: Time, t1: Time, first_t: Time, query: F)
+        where F: Fn(Time, Time) -> Vec<Point>
+    {
+        let t0 = t0.max(first_t);
+        let t1 = t1.max(t0);
+        if t0 >= self.t1 || t1 <= self.t0 || self.t0 < first_t {
+            for c in self.counts.iter_mut() {
+                c.iter_mut().for_each(|n| *n = 0);
+            }
+            self.add(&query(t0, t1), true);
+        } else {
+            if t0 < self.t0 {
+                self.add(&query(t0, self.t0), true);
+            } else if t0 > self.t0 {
+                self.add(&query(self.t0, t0), false);
+            }
+            if t1 > self.t1 {
+                self.add(&query(self.t1, t1), true);
+            } else if t1 < self.t1 {
+                self.add(&query(t1, self.t1), false);
+            }
+        }
+        self.t0 = t0;
+        self.t1 = t1;
+    }
+
+    /// Add `points` to the counts, or remove them if `add` is false.
+    fn add(&mut self, points: &[Point], add: bool) {
+        let bins = self.bins();
+        let range = (self.v1 as f64 - self.v0 as f64).max(1.0);
+        for p in points {
+            for (ch, counts) in self.counts.iter_mut().enumerate() {
+                let v = match p.vals().get(ch) {
+                    Some(v) if *v >= self.v0 && *v <= self.v1 => *v,
+                    _ => continue,
+                };
+                let bin = (((v - self.v0) as f64 / range * bins as f64) as usize).min(bins - 1);
+                if add {
+                    counts[bin] += 1;
+                } else {
+                    counts[bin] = counts[bin].saturating_sub(1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Histogram;
+    use crate::{Point, Time};
+    use crate::test_util::points;
+
+    /// Points at t = 0, 10, 20, ... 1100 with channel 0 = t / 10.
+    fn query(t0: Time, t1: Time) -> Vec<Point> {
+        points(0, 0, &(0..=110).collect::<Vec<u16>>()).into_iter()
+            .filter(|p| p.t >= t0 && p.t < t1)
+            .collect()
+    }
+
+    #[test]
+    fn incremental_matches_full() {
+        let mut inc = Histogram::new(1, 4, 0, 40);
+        inc.update(0, 200, 0, query);
+        inc.update(50, 300, 0, query);
+        inc.update(100, 250, 0, query);
+        inc.update(80, 260, 0, query);
+
+        let mut full = Histogram::new(1, 4, 0, 40);
+        full.update(80, 260, 0, query);
+        assert_eq!(inc.counts(0), full.counts(0));
+        // Values 8..=25, bins of 10.
+        assert_eq!(full.counts(0), &[2, 10, 6, 0]);
+
+        // No overlap: counted from scratch.
+        inc.update(1000, 1100, 0, query);
+        assert_eq!(inc.counts(0), &[0, 0, 0, 0]);
+        assert!(inc.has_bins(4, 0, 40));
+    }
+
+    #[test]
+    fn discarded_points() {
+        let mut h = Histogram::new(1, 4, 0, 40);
+        h.update(0, 400, 0, query);
+        assert_eq!(h.counts(0), &[10, 10, 10, 10]);
+
+        // Points before t = 150 are discarded, so can't be queried to
+        // remove them: the window still starts at 0 but only later
+        // points are counted.
+        let discarded = |t0: Time, t1: Time| query(t0.max(150), t1.max(150));
+        h.update(0, 400, 150, discarded);
+        assert_eq!(h.counts(0), &[0, 5, 10, 10]);
+        h.update(0, 400, 150, discarded);
+        assert_eq!(h.counts(0), &[0, 5, 10, 10]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Stacked lanes: horizontal strips of the graph that each draw some
+//! of the channels, see `Graph::set_lanes()`.
+
+use crate::{Error, Result, Spectrogram, Value};
+use std::collections::BTreeMap;
+
+/// A horizontal strip of the graph, with its own value range, that
+/// draws a group of channels.
+///
+/// Lanes are stacked from the top of the graph in the order given, and
+/// share the graph's height equally. While lanes are set, the value
+/// range of the `View` is not used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lane {
+    /// How the lane draws its channels.
+    pub style: LaneStyle,
+
+    /// The channels drawn in this lane. A channel may be in several
+    /// lanes, or none, in which case it is not drawn.
+    pub channels: Vec<usize>,
+
+    /// A label drawn at the top left of the lane. May be empty.
+    pub label: String,
+
+    /// The value shown at the bottom of the lane, or the magnitude at
+    /// the bottom of the colormap for `LaneStyle::Spectrogram`. Not
+    /// used by digital lanes.
+    pub value_min: Value,
+
+    /// The value shown at the top of the lane, or the magnitude at the
+    /// top of the colormap for `LaneStyle::Spectrogram`. Not used by
+    /// digital lanes.
+    pub value_max: Value,
+}
+
+/// How a `Lane` draws its channels.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LaneStyle {
+    /// Points, like the rest of the graph.
+    Analog,
+
+    /// A logic trace for each channel, one above the other: high where
+    /// the value is non-zero and low where it is zero, with a line at
+    /// each transition.
+    Logic,
+
+    /// A bar for each channel, one above the other, divided where the
+    /// value changes and labeled with the name of each state. Values
+    /// missing from the map are labeled with the number.
+    States(BTreeMap<Value, String>),
+
+    /// A heatmap: the channels by index from the bottom of the lane
+    /// up, colored by value. See `Spectrogram`.
+    Spectrogram(Spectrogram),
+}
+
+impl LaneStyle {
+    /// Returns true if channels are drawn at the y position of their
+    /// values, i.e. for `Analog`.
+    pub(crate) fn plots_values(&self) -> bool {
+        *self == LaneStyle::Analog
+    }
+
+    /// Returns true if the value range is used, i.e. for `Analog` and
+    /// `Spectrogram`.
+    pub(crate) fn uses_value_range(&self) -> bool {
+        matches!(self, LaneStyle::Analog | LaneStyle::Spectrogram(_))
+    }
+}
+
+/// Returns an error if a lane draws a channel that is not below
+/// `num_channels`, or uses a value range that is empty.
+pub(crate) fn check_lanes(lanes: &[Lane], num_channels: usize) -> Result<()> {
+    for lane in lanes.iter() {
+        if let Some(ch) = lane.channels.iter().find(|ch| **ch >= num_channels) {
+            return Err(Error::String(format!("Invalid channel index {}", ch)));
+        }
+        if lane.style.uses_value_range() && lane.value_min >= lane.value_max {
+            return Err(Error::String(format!(
+                "Lane {:?} value_min must be < value_max", lane.label)));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the top and height in pixels of lane `i` of `n` in a graph
+/// `h` pixels high. Lanes cover the graph exactly, with no gaps.
+pub(crate) fn lane_rect(i: usize, n: usize, h: usize) -> (usize, usize) {
+    let top = i * h / n;
+    let bottom = (i + 1) * h / n;
+    (top, bottom - top)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_lanes, lane_rect, Lane, LaneStyle};
+
+    #[test]
+    fn lanes_cover_graph() {
+        assert_eq!(lane_rect(0, 1, 200), (0, 200));
+        assert_eq!(lane_rect(0, 3, 200), (0, 66));
+        assert_eq!(lane_rect(1, 3, 200), (66, 67));
+        assert_eq!(lane_rect(2, 3, 200), (133, 67));
+    }
+
+    #[test]
+    fn invalid_lanes() {
+        let lane = |channels: Vec<usize>, style, value_max| Lane {
+            style,
+            channels,
+            label: "lane".to_owned(),
+            value_min: 10,
+            value_max,
+        };
+        assert!(check_lanes(&[lane(vec![0, 1], LaneStyle::Analog, 20)], 2).is_ok());
+        assert!(check_lanes(&[lane(vec![0, 2], LaneStyle::Analog, 20)], 2).is_err());
+        assert!(check_lanes(&[lane(vec![0], LaneStyle::Analog, 10)], 2).is_err());
+        // Logic lanes don't use their value range.
+        assert!(check_lanes(&[lane(vec![0], LaneStyle::Logic, 10)], 2).is_ok());
+    }
+}
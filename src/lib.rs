@@ -15,26 +15,69 @@ extern crate log;
 
 use std::fmt::Debug;
 
+mod annotation;
+pub use annotation::Annotation;
+
+mod axis;
+pub use axis::TimeAxisLabels;
+
+mod digital;
+
+mod fft;
+pub use fft::WindowFunction;
+
 mod graph;
-pub use graph::{Config, ConfigBuilder, Graph, PointStyle, View, ViewMode};
+pub use graph::{Channels, Config, ConfigBuilder, Cursor, Cursors, DragAction, Graph, PointStyle,
+                View, ViewMode};
 
 mod graph_with_controls;
 pub use graph_with_controls::GraphWithControls;
 
+mod histogram;
+
+mod lane;
+pub use lane::{Lane, LaneStyle};
+
 mod null_data_source;
 pub use null_data_source::NullDataSource;
 
 pub mod observable_value;
 
+mod phosphor;
+pub use phosphor::Phosphor;
+
+mod pixel_map;
+
 mod signal;
 pub use signal::Signal;
 
+mod spectrogram;
+pub use spectrogram::{Colormap, MagnitudeScale, Spectrogram};
+
+mod spectrum;
+pub use spectrum::{Spectrum, SpectrumConfig, SpectrumConfigBuilder};
+
 mod store;
 use store::Store;
 
 mod test_data_generator;
 pub use test_data_generator::TestDataGenerator;
 
+#[cfg(test)]
+mod test_util;
+
+mod threshold;
+pub use threshold::{Band, Crossing, Threshold, ThresholdDirection};
+
+mod trigger;
+pub use trigger::{Trigger, TriggerEdge, TriggerSweep};
+
+mod wall_clock;
+pub use wall_clock::{WallClock, WallClockBuilder};
+
+mod xy_plot;
+pub use xy_plot::{XyPlot, XyPlotConfig, XyPlotConfigBuilder};
+
 /// Represents an error that could occur using the crate
 #[derive(Debug)]
 pub enum Error {
@@ -46,7 +89,10 @@ pub enum Error {
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// A point in time when a data point was emitted.
-pub type Time = u32;
+///
+/// The units are chosen by the `DataSource`. Use a `WallClock` in the
+/// `Config` to map `Time` values to wall-clock time for display.
+pub type Time = u64;
 
 /// The value of a data point
 pub type Value = u16;
@@ -77,7 +123,7 @@ impl Point {
 ///
 /// The tuple values are the red, green, and blue components of the
 /// color respectively.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Color(pub u8, pub u8, pub u8);
 
 impl Color {
@@ -102,19 +148,6 @@ impl Color {
     }
 }
 
-#[cfg(test)]
-mod color_tests {
-    use super::Color;
-
-    #[test]
-    fn values() {
-        let c = Color::from_rgb(10, 20, 30);
-        assert_eq!(c.r(), 10);
-        assert_eq!(c.g(), 20);
-        assert_eq!(c.b(), 30);
-    }
-}
-
 /// Implement this to get your own data into a `Graph`.
 pub trait DataSource: Debug + Send {
     /// Return whatever points you have available when this method is called.
@@ -142,4 +175,44 @@ pub trait DataSource: Debug + Send {
                 Color(0u8,   0u8,   255u8)
         ])
     }
+
+    /// Return the names of each value of the graph, used to label them
+    /// in the legend.
+    ///
+    /// By default the values are named "Value 0", "Value 1", and so on.
+    /// If you don't supply enough names for the number of values
+    /// returned, the rest are given default names.
+    fn get_names(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Return the units of each value of the graph, e.g. "V" or "°C",
+    /// shown after values in the hover tooltip.
+    ///
+    /// By default values have no units. If you don't supply enough
+    /// units for the number of values returned, the rest have none.
+    fn get_units(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Return any new annotations to draw on the graph, e.g. events
+    /// that happened since the last call. Called along with `get_data`.
+    ///
+    /// By default there are none.
+    fn get_annotations(&mut self) -> Result<Vec<Annotation>> {
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod color_tests {
+    use super::Color;
+
+    #[test]
+    fn values() {
+        let c = Color::from_rgb(10, 20, 30);
+        assert_eq!(c.r(), 10);
+        assert_eq!(c.g(), 20);
+        assert_eq!(c.b(), 30);
+    }
 }
@@ -20,3 +20,9 @@ impl NullDataSource {
         NullDataSource
     }
 }
+
+impl Default for NullDataSource {
+    fn default() -> NullDataSource {
+        NullDataSource::new()
+    }
+}
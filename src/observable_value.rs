@@ -88,7 +88,7 @@ impl<T> ObservableValue<T>
                 inner: inner.clone(),
             },
             WriteHalf {
-                inner: inner
+                inner
             }
         )
     }
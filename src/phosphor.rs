@@ -0,0 +1,154 @@
+//! Phosphor-style persistence: points are accumulated as hits per
+//! pixel, and intensity is drawn with a color gradient, see
+//! `ConfigBuilder::phosphor()`.
+
+use crate::Color;
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Settings for drawing the graph like a digital phosphor scope.
+///
+/// Instead of each channel's color, pixels are colored by how many
+/// points hit them. In `ViewMode::Triggered` hits accumulate over
+/// sweeps, fading by `decay` at each new sweep, which shows the
+/// distribution of overlapping traces. Threshold highlight colors are
+/// not used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Phosphor {
+    /// The fraction of intensity kept at each new sweep, from 0.0 (only
+    /// the latest sweep is shown) to 1.0 (sweeps never fade).
+    pub decay: f32,
+
+    /// The intensity that reaches the end of `gradient`. One point
+    /// adds 1.0 to the intensity of each pixel it is drawn on.
+    pub saturation: f32,
+
+    /// The colors to draw from low to high intensity. Intensity is
+    /// mapped on a log scale, so single hits stay visible.
+    pub gradient: Vec<Color>,
+}
+
+impl Default for Phosphor {
+    fn default() -> Phosphor {
+        Phosphor {
+            decay: 0.9,
+            saturation: 50.0,
+            gradient: vec![Color(0, 60, 0), Color(0, 255, 0), Color(255, 255, 255)],
+        }
+    }
+}
+
+impl Phosphor {
+    /// Returns the color to draw a pixel with intensity `i`.
+    pub(crate) fn color(&self, i: f32) -> Color {
+        let f = ((1.0 + i).ln() / (1.0 + self.saturation.max(1.0)).ln()).clamp(0.0, 1.0);
+        gradient_color(&self.gradient, f)
+    }
+}
+
+/// Returns the color a fraction `f` (0.0 to 1.0) along `gradient`.
+pub(crate) fn gradient_color(gradient: &[Color], f: f32) -> Color {
+    let n = gradient.len();
+    if n == 0 {
+        return Color(255, 255, 255);
+    }
+    let pos = f * (n - 1) as f32;
+    let i = (pos.floor() as usize).min(n - 1);
+    let (a, b) = (gradient[i], gradient[(i + 1).min(n - 1)]);
+    let frac = pos - i as f32;
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * frac).round() as u8;
+    Color(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+/// Hits per pixel in a patch buffer.
+pub(crate) struct Intensity {
+    w: usize,
+    h: usize,
+    vals: Vec<f32>,
+}
+
+impl Intensity {
+    pub(crate) fn new(w: usize, h: usize) -> Intensity {
+        Intensity {
+            w,
+            h,
+            vals: vec![0.0; w * h],
+        }
+    }
+
+    /// Add a hit at pixel (x, y), if it is inside the buffer.
+    pub(crate) fn hit(&mut self, x: usize, y: usize) {
+        if x < self.w && y < self.h {
+            self.vals[self.w * y + x] += 1.0;
+        }
+    }
+
+    /// Multiply every pixel's intensity by `decay`.
+    pub(crate) fn decay(&mut self, decay: f32) {
+        let decay = decay.clamp(0.0, 1.0);
+        for v in &mut self.vals {
+            *v *= decay;
+        }
+    }
+
+    /// Draw the buffer into patch bytes of the same size, leaving
+    /// pixels with no intensity transparent.
+    pub(crate) fn to_bytes(&self, pb: &mut [u8], phosphor: &Phosphor) {
+        assert_eq!(pb.len(), self.vals.len() * BYTES_PER_PIXEL);
+        for (p, &i) in self.vals.iter().enumerate() {
+            // Skip pixels that have all but faded away.
+            if i < 0.01 {
+                continue;
+            }
+            let col = phosphor.color(i);
+            let b = BYTES_PER_PIXEL * p;
+            pb[b+2] = col.0; // R
+            pb[b+1] = col.1; // G
+            pb[b]   = col.2; // B
+            pb[b+3] = 255;   // A
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{gradient_color, Intensity, Phosphor};
+    use crate::Color;
+
+    #[test]
+    fn gradient() {
+        let g = [Color(0, 0, 0), Color(100, 200, 0), Color(200, 200, 200)];
+        assert_eq!(gradient_color(&g, 0.0), Color(0, 0, 0));
+        assert_eq!(gradient_color(&g, 0.25), Color(50, 100, 0));
+        assert_eq!(gradient_color(&g, 0.5), Color(100, 200, 0));
+        assert_eq!(gradient_color(&g, 1.0), Color(200, 200, 200));
+        assert_eq!(gradient_color(&g[..1], 0.7), Color(0, 0, 0));
+    }
+
+    #[test]
+    fn hits_and_decay() {
+        let ph = Phosphor {
+            decay: 0.5,
+            saturation: 3.0,
+            gradient: vec![Color(0, 0, 0), Color(0, 0, 255)],
+        };
+        let mut i = Intensity::new(2, 1);
+        i.hit(0, 0);
+        i.hit(0, 0);
+        i.hit(0, 0);
+        i.hit(5, 0);
+        let mut pb = vec![0u8; 8];
+        i.to_bytes(&mut pb, &ph);
+        // Saturated, blue at the end of the gradient; the other pixel
+        // has no hits and stays transparent.
+        assert_eq!(pb, vec![255, 0, 0, 255, 0, 0, 0, 0]);
+
+        i.decay(ph.decay);
+        i.decay(ph.decay);
+        let mut pb = vec![0u8; 8];
+        i.to_bytes(&mut pb, &ph);
+        // Intensity 0.75 is between the ends of the gradient.
+        assert!(pb[0] > 0 && pb[0] < 255);
+        assert_eq!(pb[3], 255);
+    }
+}
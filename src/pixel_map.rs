@@ -0,0 +1,99 @@
+//! Exact mapping between `Time` values and pixel columns.
+//!
+//! Pixel column `px` shows times from `pixel_to_t(px)` up to but not
+//! including `pixel_to_t(px + 1)`, counting columns from time 0. Each
+//! boundary is computed from its pixel index rather than by adding up
+//! rounded steps, so patches drawn separately at any zoom level line
+//! up with no gaps or overlaps.
+
+use crate::Time;
+
+/// Returns the time at the left boundary of pixel column `px`.
+pub(crate) fn pixel_to_t(px: i64, zoom_x: f64) -> Time {
+    (px as f64 * zoom_x).floor().max(0.0) as Time
+}
+
+/// Returns the pixel column that shows time `t`.
+pub(crate) fn t_to_pixel(t: Time, zoom_x: f64) -> i64 {
+    let mut px = (t as f64 / zoom_x).floor().max(0.0) as i64;
+    // Correct for floating point error, so that
+    // pixel_to_t(px) <= t < pixel_to_t(px + 1).
+    while px > 0 && pixel_to_t(px, zoom_x) > t {
+        px -= 1;
+    }
+    while px < i64::MAX {
+        let next = pixel_to_t(px + 1, zoom_x);
+        if next > t {
+            break;
+        }
+        px += 1;
+        // Boundaries saturate at Time::MAX, so no later column starts
+        // after t.
+        if next == Time::MAX {
+            break;
+        }
+    }
+    px
+}
+
+/// Returns the pixel boundary at or before `t`.
+pub(crate) fn snap_t(t: Time, zoom_x: f64) -> Time {
+    pixel_to_t(t_to_pixel(t, zoom_x), zoom_x)
+}
+
+/// Returns the index of the pixel boundary at time `t`, or None if `t`
+/// is not on a pixel boundary.
+pub(crate) fn pixel_index(t: Time, zoom_x: f64) -> Option<i64> {
+    let px = t_to_pixel(t, zoom_x);
+    if pixel_to_t(px, zoom_x) == t { Some(px) } else { None }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pixel_index, pixel_to_t, snap_t, t_to_pixel};
+    use crate::Time;
+
+    #[test]
+    fn round_trip() {
+        for &zoom_x in &[1.0, 2.0, 3.7, 0.3, 1000.0 / 3.0, 1e-3] {
+            for px in 0..2000 {
+                let t = pixel_to_t(px, zoom_x);
+                if pixel_to_t(px + 1, zoom_x) > t {
+                    assert_eq!(t_to_pixel(t, zoom_x), px, "zoom_x={}", zoom_x);
+                    assert_eq!(pixel_index(t, zoom_x), Some(px));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn times_in_columns() {
+        let zoom_x = 3.7;
+        for t in 0..10_000 {
+            let px = t_to_pixel(t, zoom_x);
+            assert!(pixel_to_t(px, zoom_x) <= t);
+            assert!(t < pixel_to_t(px + 1, zoom_x));
+        }
+        assert_eq!(snap_t(8, zoom_x), 7);
+        assert_eq!(pixel_index(8, zoom_x), None);
+    }
+
+    #[test]
+    fn large_times() {
+        let zoom_x = 1234.5678;
+        let t = 1_700_000_000_000_000;
+        let px = t_to_pixel(t, zoom_x);
+        assert!(pixel_to_t(px, zoom_x) <= t);
+        assert!(t < pixel_to_t(px + 1, zoom_x));
+    }
+
+    #[test]
+    fn max_time() {
+        // Boundaries saturate at Time::MAX, which must not loop forever.
+        for &zoom_x in &[1.0, 1234.5678, 1e-3] {
+            let px = t_to_pixel(Time::MAX, zoom_x);
+            assert!(px >= t_to_pixel(Time::MAX / 2, zoom_x));
+            assert_eq!(snap_t(Time::MAX, zoom_x), pixel_to_t(px, zoom_x));
+        }
+    }
+}
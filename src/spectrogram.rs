@@ -0,0 +1,143 @@
+//! Drawing vector-valued points as a heatmap, see
+//! `ConfigBuilder::spectrogram()`.
+
+use crate::{Color, phosphor::gradient_color, Value};
+
+/// Settings for drawing each point as a vertical heatmap strip: the
+/// point's values by index from the bottom up, colored by magnitude.
+///
+/// The value range of the `View` (or `Lane`) sets the magnitudes at the
+/// ends of the colormap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spectrogram {
+    /// The colors magnitudes are drawn with.
+    pub colormap: Colormap,
+
+    /// How magnitudes are mapped onto the colormap.
+    pub scale: MagnitudeScale,
+}
+
+impl Default for Spectrogram {
+    fn default() -> Spectrogram {
+        Spectrogram {
+            colormap: Colormap::Viridis,
+            scale: MagnitudeScale::Linear,
+        }
+    }
+}
+
+/// The colors a `Spectrogram` draws magnitudes with, from low to high.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Colormap {
+    /// Black to white.
+    Grayscale,
+
+    /// Dark blue through green to yellow.
+    Viridis,
+
+    /// Black through red and orange to pale yellow.
+    Inferno,
+
+    /// Colors evenly spaced from low to high.
+    Custom(Vec<Color>),
+}
+
+impl Colormap {
+    fn stops(&self) -> &[Color] {
+        match self {
+            Colormap::Grayscale => &[Color(0, 0, 0), Color(255, 255, 255)],
+            Colormap::Viridis => &[
+                Color(68, 1, 84), Color(59, 82, 139), Color(33, 145, 140),
+                Color(94, 201, 98), Color(253, 231, 37),
+            ],
+            Colormap::Inferno => &[
+                Color(0, 0, 4), Color(87, 16, 110), Color(188, 55, 84),
+                Color(249, 142, 9), Color(252, 255, 164),
+            ],
+            Colormap::Custom(colors) => colors,
+        }
+    }
+}
+
+/// How a `Spectrogram` maps magnitudes onto its colormap.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MagnitudeScale {
+    /// Evenly from the bottom to the top of the value range.
+    Linear,
+
+    /// Logarithmically from the bottom of the value range, which shows
+    /// more detail in low magnitudes.
+    Log,
+
+    /// In decibels below the top of the value range, showing `range` dB.
+    Decibels {
+        /// The number of decibels from the bottom of the colormap to
+        /// the top, e.g. 60.0.
+        range: f64,
+    },
+}
+
+impl Spectrogram {
+    /// Returns the color to draw magnitude `v`, where v0 and v1 are the
+    /// magnitudes at the ends of the colormap.
+    pub(crate) fn color(&self, v: Value, v0: Value, v1: Value) -> Color {
+        gradient_color(self.colormap.stops(), self.fraction(v, v0, v1) as f32)
+    }
+
+    /// Returns where magnitude `v` is along the colormap, from 0.0 to 1.0.
+    fn fraction(&self, v: Value, v0: Value, v1: Value) -> f64 {
+        if v1 <= v0 {
+            return 0.0;
+        }
+        let v = v.clamp(v0, v1);
+        let f = match self.scale {
+            MagnitudeScale::Linear => (v - v0) as f64 / (v1 - v0) as f64,
+            MagnitudeScale::Log => (1.0 + (v - v0) as f64).ln() / (1.0 + (v1 - v0) as f64).ln(),
+            MagnitudeScale::Decibels { range } => {
+                if v == 0 || range <= 0.0 {
+                    return 0.0;
+                }
+                let db = 20.0 * (v as f64 / v1 as f64).log10();
+                1.0 + db / range
+            },
+        };
+        f.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Colormap, MagnitudeScale, Spectrogram};
+    use crate::Color;
+
+    fn spectrogram(scale: MagnitudeScale) -> Spectrogram {
+        Spectrogram {
+            colormap: Colormap::Grayscale,
+            scale,
+        }
+    }
+
+    #[test]
+    fn scales() {
+        let lin = spectrogram(MagnitudeScale::Linear);
+        assert_eq!(lin.fraction(50, 0, 100), 0.5);
+        assert_eq!(lin.fraction(150, 0, 100), 1.0);
+        assert_eq!(lin.fraction(5, 10, 100), 0.0);
+
+        let log = spectrogram(MagnitudeScale::Log);
+        assert!(log.fraction(10, 0, 1000) > 0.3);
+        assert_eq!(log.fraction(1000, 0, 1000), 1.0);
+
+        let db = spectrogram(MagnitudeScale::Decibels { range: 40.0 });
+        assert!((db.fraction(100, 0, 1000) - 0.5).abs() < 1e-9);
+        assert!((db.fraction(10, 0, 1000) - 0.0).abs() < 1e-9);
+        assert_eq!(db.fraction(1, 0, 1000), 0.0);
+    }
+
+    #[test]
+    fn colors() {
+        let lin = spectrogram(MagnitudeScale::Linear);
+        assert_eq!(lin.color(0, 0, 100), Color(0, 0, 0));
+        assert_eq!(lin.color(100, 0, 100), Color(255, 255, 255));
+    }
+}
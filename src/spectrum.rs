@@ -0,0 +1,317 @@
+use crate::{
+    axis::{decimals_for_step, draw_bottom_tick, draw_left_tick, nice_step, set_source_color,
+           ticks},
+    Color, Error, fft::{amplitude_spectrum, resample}, Graph, Result, Value, WindowFunction,
+};
+use gtk::prelude::*;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+
+/// Width of the magnitude axis to the left of the plot in pixels.
+const MAGNITUDE_AXIS_WIDTH: f64 = 50.0;
+/// Height of the frequency axis under the plot in pixels.
+const FREQUENCY_AXIS_HEIGHT: f64 = 20.0;
+/// Minimum distance between ticks on the frequency axis in pixels.
+const FREQUENCY_AXIS_MIN_TICK_SPACING: f64 = 80.0;
+/// Minimum distance between ticks on the magnitude axis in pixels.
+const MAGNITUDE_AXIS_MIN_TICK_SPACING: f64 = 30.0;
+
+/// The configuration of a `Spectrum`.
+///
+/// Create an instance using a `SpectrumConfigBuilder`.
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct SpectrumConfig {
+    /// The channel of the graph's data to analyse. This can be changed
+    /// later with `Spectrum::set_channel()`.
+    #[builder(default = "0")]
+    channel: usize,
+
+    /// How many of the most recent samples to analyse. Rounded up to a
+    /// power of 2.
+    #[builder(default = "1024")]
+    samples: usize,
+
+    /// The window function applied to the samples.
+    #[builder(default = "WindowFunction::Hann")]
+    window: WindowFunction,
+
+    /// How often the spectrum is recomputed.
+    #[builder(default = "Duration::from_millis(200)")]
+    refresh_interval: Duration,
+
+    /// How many spectra to average over, exponentially. 1 shows each
+    /// spectrum as it is computed.
+    #[builder(default = "1")]
+    averages: u32,
+
+    /// Whether to draw the highest magnitude seen in each frequency bin.
+    /// This can be changed later with `Spectrum::set_peak_hold()`.
+    #[builder(default = "false")]
+    peak_hold: bool,
+
+    /// The range of magnitudes shown, in dB below full scale
+    /// (`Value::MAX`).
+    #[builder(default = "80.0")]
+    db_range: f64,
+
+    /// Initial width in pixels.
+    #[builder(default = "400")]
+    width: u32,
+
+    /// Initial height in pixels.
+    #[builder(default = "200")]
+    height: u32,
+
+    /// The color of the spectrum.
+    #[builder(default = "Color(0, 255, 0)")]
+    color: Color,
+
+    /// The color of the peak-hold trace.
+    #[builder(default = "Color(255, 96, 96)")]
+    peak_color: Color,
+}
+
+/// A GTK widget that draws the frequency spectrum of the latest data
+/// of one channel of a `Graph`.
+///
+/// Samples at irregular intervals are resampled to even intervals
+/// before the FFT. Frequencies are in Hz if the graph has a
+/// `WallClock`, otherwise in cycles per `Time` unit.
+pub struct Spectrum {
+    s: Rc<State>,
+}
+
+struct State {
+    config: SpectrumConfig,
+    graph: Graph,
+    drawing_area: gtk::DrawingArea,
+
+    channel: Cell<usize>,
+    peak_hold: Cell<bool>,
+    /// The averaged amplitude of each frequency bin.
+    amplitudes: RefCell<Vec<f64>>,
+    /// The highest amplitude seen in each frequency bin.
+    peaks: RefCell<Vec<f64>>,
+    /// The frequency of each bin above the previous one.
+    bin_width: Cell<f64>,
+}
+
+impl Spectrum {
+    /// Build and show a `Spectrum` widget for `graph`'s data in the
+    /// target `gtk::Container`.
+    ///
+    /// Returns an error if the configured channel is not a valid
+    /// channel index.
+    pub fn build_ui<C>(config: SpectrumConfig, graph: &Graph, container: &C) -> Result<Spectrum>
+        where C: IsA<gtk::Container> + IsA<gtk::Widget>
+    {
+        check_channel(graph, config.channel)?;
+
+        let drawing_area = gtk::DrawingAreaBuilder::new()
+            .height_request(config.height as i32)
+            .width_request(config.width as i32)
+            .build();
+        container.add(&drawing_area);
+
+        let s = Rc::new(State {
+            channel: Cell::new(config.channel),
+            peak_hold: Cell::new(config.peak_hold),
+            amplitudes: RefCell::new(vec![]),
+            peaks: RefCell::new(vec![]),
+            bin_width: Cell::new(0.0),
+
+            graph: graph.clone(),
+            drawing_area: drawing_area.clone(),
+            config,
+        });
+
+        let sc = s.clone();
+        drawing_area.connect_draw(move |_ctrl, ctx| {
+            spectrum_draw(ctx, &sc)
+        });
+
+        let sc = s.clone();
+        let refresh_source = glib::source::timeout_add_local(
+            s.config.refresh_interval.as_millis().max(1) as u32,
+            move || {
+                refresh(&sc);
+                Continue(true)
+            });
+
+        // Stop refreshing once the widget is gone.
+        let refresh_source = Cell::new(Some(refresh_source));
+        drawing_area.connect_destroy(move |_ctrl| {
+            if let Some(id) = refresh_source.take() {
+                glib::source::source_remove(id);
+            }
+        });
+
+        drawing_area.show_all();
+
+        Ok(Spectrum {
+            s,
+        })
+    }
+
+    /// Analyse channel `ch`. Averages and peaks start again.
+    ///
+    /// Returns an error if `ch` is not a valid channel index.
+    pub fn set_channel(&self, ch: usize) -> Result<()> {
+        check_channel(&self.s.graph, ch)?;
+        self.s.channel.set(ch);
+        self.s.amplitudes.borrow_mut().clear();
+        self.s.peaks.borrow_mut().clear();
+        Ok(())
+    }
+
+    /// Return the channel being analysed.
+    pub fn channel(&self) -> usize {
+        self.s.channel.get()
+    }
+
+    /// Turn the peak-hold trace on or off. Turning it on starts from
+    /// the current spectrum.
+    pub fn set_peak_hold(&self, peak_hold: bool) {
+        self.s.peak_hold.set(peak_hold);
+        self.s.peaks.borrow_mut().clear();
+        self.s.drawing_area.queue_draw();
+    }
+
+    /// Start the peak-hold trace again from the current spectrum.
+    pub fn reset_peak_hold(&self) {
+        self.s.peaks.borrow_mut().clear();
+    }
+
+    /// Return the current (averaged) spectrum, as pairs of frequency
+    /// and amplitude in the units of the channel's values.
+    pub fn spectrum(&self) -> Vec<(f64, f64)> {
+        let bin_width = self.s.bin_width.get();
+        self.s.amplitudes.borrow().iter().enumerate()
+            .map(|(k, a)| (k as f64 * bin_width, *a))
+            .collect()
+    }
+
+    /// Return the `gtk::DrawingArea` the spectrum is drawn on.
+    pub fn drawing_area(&self) -> gtk::DrawingArea {
+        self.s.drawing_area.clone()
+    }
+}
+
+/// Returns an error if `ch` is not a valid channel index of `graph`.
+fn check_channel(graph: &Graph, ch: usize) -> Result<()> {
+    if ch >= graph.channel_names().len() {
+        return Err(Error::String(format!("Invalid channel index {}", ch)));
+    }
+    Ok(())
+}
+
+/// Compute a new spectrum from the latest data, and fold it into the
+/// averages and peaks.
+fn refresh(s: &State) {
+    let n = s.config.samples.max(2).next_power_of_two();
+    let ch = s.channel.get();
+    let samples: Vec<_> = s.graph.latest_points(n).iter()
+        .map(|p| (p.t, p.vals()[ch] as f64))
+        .collect();
+    let (uniform, dt) = match resample(&samples, n) {
+        Some(r) => r,
+        None => return,
+    };
+    let new = amplitude_spectrum(&uniform, s.config.window);
+
+    let secs_per_t = s.graph.wall_clock()
+        .map(|wc| wc.time_unit().as_secs_f64())
+        .unwrap_or(1.0);
+    s.bin_width.set(1.0 / (n as f64 * dt * secs_per_t));
+
+    let mut amplitudes = s.amplitudes.borrow_mut();
+    if amplitudes.len() != new.len() {
+        *amplitudes = new;
+    } else {
+        let alpha = 1.0 / s.config.averages.max(1) as f64;
+        for (a, x) in amplitudes.iter_mut().zip(new.iter()) {
+            *a += alpha * (x - *a);
+        }
+    }
+
+    if s.peak_hold.get() {
+        let mut peaks = s.peaks.borrow_mut();
+        if peaks.len() != amplitudes.len() {
+            *peaks = amplitudes.clone();
+        } else {
+            for (p, a) in peaks.iter_mut().zip(amplitudes.iter()) {
+                *p = p.max(*a);
+            }
+        }
+    }
+    s.drawing_area.queue_draw();
+}
+
+/// Returns an amplitude in dB below full scale.
+fn to_db(amplitude: f64) -> f64 {
+    20.0 * (amplitude.max(1e-12) / Value::MAX as f64).log10()
+}
+
+/// Handle the spectrum's draw signal.
+fn spectrum_draw(ctx: &cairo::Context, s: &State) -> Inhibit {
+    let c = &s.config;
+    let w = s.drawing_area.get_allocated_width() as f64;
+    let h = s.drawing_area.get_allocated_height() as f64;
+    let (plot_x, plot_w) = (MAGNITUDE_AXIS_WIDTH, (w - MAGNITUDE_AXIS_WIDTH).max(1.0));
+    let plot_h = (h - FREQUENCY_AXIS_HEIGHT).max(1.0);
+
+    s.graph.fill_background(ctx, plot_x, 0.0, plot_w, plot_h);
+
+    let amplitudes = s.amplitudes.borrow();
+    let bins = amplitudes.len();
+    let max_f = bins.saturating_sub(1) as f64 * s.bin_width.get();
+    let db_range = c.db_range.max(1.0);
+    let y_for = |a: f64| (-to_db(a) / db_range).clamp(0.0, 1.0) * plot_h;
+    let x_for = |k: usize| plot_x + k as f64 / (bins - 1).max(1) as f64 * plot_w;
+
+    // Axes
+    s.graph.set_axis_style(ctx);
+    ctx.set_line_width(1.0);
+    let db_step = nice_step(MAGNITUDE_AXIS_MIN_TICK_SPACING * db_range / plot_h);
+    for db in ticks(-db_range, 0.0, db_step) {
+        let y = (-db / db_range * plot_h).round() + 0.5;
+        draw_left_tick(ctx, plot_x, y, &format!("{}", db), 0.0, plot_h);
+    }
+    if max_f > 0.0 {
+        let f_step = nice_step(FREQUENCY_AXIS_MIN_TICK_SPACING * max_f / plot_w);
+        let unit = if s.graph.wall_clock().is_some() { "Hz" } else { "/t" };
+        for f in ticks(0.0, max_f, f_step) {
+            let x = (plot_x + f / max_f * plot_w).round() + 0.5;
+            let label = format!("{:.*}{}", decimals_for_step(f_step), f, unit);
+            draw_bottom_tick(ctx, x, plot_h, &label, h - 4.0, w);
+        }
+    }
+
+    // Traces
+    if bins < 2 {
+        return Inhibit(false);
+    }
+    ctx.save();
+    ctx.rectangle(plot_x, 0.0, plot_w, plot_h);
+    ctx.clip();
+    let peaks = s.peaks.borrow();
+    if s.peak_hold.get() && peaks.len() == bins {
+        set_source_color(ctx, c.peak_color);
+        for (k, p) in peaks.iter().enumerate() {
+            ctx.line_to(x_for(k), y_for(*p));
+        }
+        ctx.stroke();
+    }
+    set_source_color(ctx, c.color);
+    for (k, a) in amplitudes.iter().enumerate() {
+        ctx.line_to(x_for(k), y_for(*a));
+    }
+    ctx.stroke();
+    ctx.restore();
+
+    Inhibit(false)
+}
@@ -1,44 +1,85 @@
-use crate::{Error, Point, Result, Time, Value};
+use crate::{Annotation, Error, Point, Result, Time, Value};
 use std::collections::BTreeMap;
 
 pub struct Store {
-    last_t: Time,
+    last_t: Option<Time>,
+    origin_t: Option<Time>,
     val_len: u8,
     all: BTreeMap<Time, Vec<Value>>,
+    /// Annotations, keyed by start time, each with the number of
+    /// annotations added before it.
+    annotations: BTreeMap<Time, Vec<(usize, Annotation)>>,
+    annotations_added: usize,
 }
 
 impl Store {
     pub fn new(val_len: u8) -> Store {
         Store {
-            last_t: 0,
+            last_t: None,
+            origin_t: None,
             val_len,
             all: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+            annotations_added: 0,
         }
     }
 
     pub fn ingest(&mut self, ps: &[Point]) -> Result<()> {
         for p in ps {
-            if p.t <= self.last_t {
+            if matches!(self.last_t, Some(last_t) if p.t <= last_t) {
                 return Err(Error::String("t <= last_t".to_owned()));
             }
-            self.last_t = p.t;
+            self.last_t = Some(p.t);
+            if self.origin_t.is_none() {
+                self.origin_t = Some(p.t);
+            }
 
             assert!(p.vs.len() == self.val_len as usize);
             self.all.insert(p.t, p.vs.clone());
         }
 
-        trace!("ingest all.len={} last_t={}", self.all.len(), self.last_t);
+        trace!("ingest all.len={} last_t={:?}", self.all.len(), self.last_t);
 
         Ok(())
     }
 
     pub fn discard(&mut self, t0: Time, t1: Time) -> Result<()> {
-        for t in self.all.range(t0..t1).map(|(t,_vs)| *t).collect::<Vec<u32>>() {
+        for t in self.all.range(t0..t1).map(|(t,_vs)| *t).collect::<Vec<Time>>() {
             self.all.remove(&t);
         }
+
+        // Discard annotations that are entirely within the range.
+        let starts: Vec<Time> = self.annotations.range(t0..t1).map(|(t, _)| *t).collect();
+        for t in starts {
+            let anns = self.annotations.get_mut(&t).unwrap();
+            anns.retain(|(_, a)| a.end_t() >= t1);
+            if anns.is_empty() {
+                self.annotations.remove(&t);
+            }
+        }
         Ok(())
     }
 
+    pub fn add_annotation(&mut self, a: Annotation) {
+        self.annotations.entry(a.t0).or_default().push((self.annotations_added, a));
+        self.annotations_added += 1;
+    }
+
+    /// Returns the annotations that overlap times t0 to t1 inclusive,
+    /// ordered by start time, each with the number of annotations added
+    /// before it.
+    pub fn query_annotations(&self, t0: Time, t1: Time) -> Vec<(usize, Annotation)> {
+        self.annotations.range(..=t1)
+            .flat_map(|(_t, anns)| anns.iter())
+            .filter(|(_, a)| a.end_t() >= t0)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+    }
+
     /// Returns a Vec of the points with t >= t0, < t1.
     pub fn query_range(&self, t0: Time, t1: Time) -> Result<Vec<Point>> {
         let rv: Vec<Point> =
@@ -57,8 +98,40 @@ impl Store {
         Ok(rv)
     }
 
+    /// Returns the latest n points, oldest first.
+    pub fn query_last(&self, n: usize) -> Result<Vec<Point>> {
+        let mut rv: Vec<Point> =
+            self.all.iter().rev().take(n)
+                .map(|(t,vs)| Point { t: *t, vs: vs.clone() })
+                .collect();
+        rv.reverse();
+        Ok(rv)
+    }
+
+    /// Returns the last point with t < given t.
+    pub fn query_before(&self, t: Time) -> Result<Option<Point>> {
+        let rv = self.all.range(..t)
+                     .map(|(t,vs)| Point { t: *t, vs: vs.clone() })
+                     .next_back();
+        Ok(rv)
+    }
+
+    /// Returns the point with t closest to the given t, preferring the
+    /// earlier point if two are equally close.
+    pub fn query_nearest(&self, t: Time) -> Result<Option<Point>> {
+        let before = self.all.range(..=t).next_back();
+        let after = self.all.range(t..).next();
+        let nearest = match (before, after) {
+            (Some(b), Some(a)) => if a.0 - t < t - b.0 { a } else { b },
+            (Some(b), None) => b,
+            (None, Some(a)) => a,
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(Point { t: *nearest.0, vs: nearest.1.clone() }))
+    }
+
     pub fn last_t(&self) -> Time {
-        self.last_t
+        self.last_t.unwrap_or(0)
     }
 
     pub fn first_t(&self) -> Time {
@@ -66,7 +139,83 @@ impl Store {
                            .map_or(0, |pt| pt.t)
     }
 
-    pub fn val_len(&self) -> u8 {
-        self.val_len
+    /// Returns the time of the first point ever ingested, even if it
+    /// has since been discarded.
+    pub fn origin_t(&self) -> Time {
+        self.origin_t.unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Store;
+    use crate::{Annotation, Color, Point, Time};
+
+    /// A store with 1 channel and points at t = 10, 20, 30.
+    fn store() -> Store {
+        let mut s = Store::new(1);
+        let points: Vec<Point> = [10, 20, 30].iter()
+            .map(|t: &Time| Point { t: *t, vs: vec![*t as u16] })
+            .collect();
+        s.ingest(&points).unwrap();
+        s
+    }
+
+    fn t(p: Option<Point>) -> Option<Time> {
+        p.map(|p| p.t)
+    }
+
+    #[test]
+    fn query_nearest() {
+        assert_eq!(t(Store::new(1).query_nearest(10).unwrap()), None);
+        let s = store();
+        assert_eq!(t(s.query_nearest(20).unwrap()), Some(20));
+        assert_eq!(t(s.query_nearest(24).unwrap()), Some(20));
+        assert_eq!(t(s.query_nearest(25).unwrap()), Some(20));
+        assert_eq!(t(s.query_nearest(26).unwrap()), Some(30));
+        assert_eq!(t(s.query_nearest(0).unwrap()), Some(10));
+        assert_eq!(t(s.query_nearest(Time::MAX).unwrap()), Some(30));
+    }
+
+    #[test]
+    fn query_before() {
+        assert_eq!(t(Store::new(1).query_before(10).unwrap()), None);
+        let s = store();
+        assert_eq!(t(s.query_before(10).unwrap()), None);
+        assert_eq!(t(s.query_before(20).unwrap()), Some(10));
+        assert_eq!(t(s.query_before(21).unwrap()), Some(20));
+        assert_eq!(t(s.query_before(Time::MAX).unwrap()), Some(30));
+    }
+
+    #[test]
+    fn query_last() {
+        assert!(Store::new(1).query_last(2).unwrap().is_empty());
+        let s = store();
+        let ts = |n| s.query_last(n).unwrap().iter().map(|p| p.t).collect::<Vec<Time>>();
+        assert_eq!(ts(0), vec![]);
+        assert_eq!(ts(2), vec![20, 30]);
+        assert_eq!(ts(3), vec![10, 20, 30]);
+        assert_eq!(ts(10), vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn annotations() {
+        let mut s = store();
+        let col = Color(255, 0, 0);
+        s.add_annotation(Annotation::marker(10, "a", col));
+        s.add_annotation(Annotation::span(15, 40, "b", col));
+        s.add_annotation(Annotation::marker(30, "c", col));
+        let texts = |s: &Store, t0, t1| s.query_annotations(t0, t1).into_iter()
+            .map(|(i, a)| (i, a.text))
+            .collect::<Vec<_>>();
+        assert_eq!(texts(&s, 0, 20), vec![(0, "a".to_owned()), (1, "b".to_owned())]);
+        assert_eq!(texts(&s, 35, 50), vec![(1, "b".to_owned())]);
+        assert_eq!(texts(&s, 41, 50), vec![]);
+
+        // The marker at 10 is discarded, the span ending after 20 is kept.
+        s.discard(0, 20).unwrap();
+        assert_eq!(texts(&s, 0, 50), vec![(1, "b".to_owned()), (2, "c".to_owned())]);
+        s.discard(0, 50).unwrap();
+        assert_eq!(texts(&s, 0, 50), vec![]);
     }
 }
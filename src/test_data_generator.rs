@@ -22,6 +22,12 @@ impl TestDataGenerator {
     }
 }
 
+impl Default for TestDataGenerator {
+    fn default() -> TestDataGenerator {
+        TestDataGenerator::new()
+    }
+}
+
 impl DataSource for TestDataGenerator {
     fn get_data(&mut self) -> Result<Vec<Point>> {
         let mut rv: Vec<Point> = Vec::with_capacity(GEN_POINTS as usize);
@@ -53,10 +59,15 @@ impl DataSource for TestDataGenerator {
     fn get_num_values(&self) -> Result<usize> {
         Ok(3)
     }
+
+    fn get_names(&self) -> Result<Vec<String>> {
+        Ok(vec!["Sine".to_owned(),
+                "Decaying sine".to_owned(),
+                "Slow sine".to_owned()])
+    }
 }
 
 fn trig_sample(scale: f32, scale_period: f32, offset: f32, t: Time) -> Value {
     let float_val = (offset + t as f32 * scale_period).sin() * scale;
-    let int_val = (((float_val + 1.0) / 2.0) * std::u16::MAX as f32) as Value;
-    int_val
+    (((float_val + 1.0) / 2.0) * Value::MAX as f32) as Value
 }
@@ -0,0 +1,15 @@
+//! Fixtures shared by the unit tests.
+
+use crate::{Point, Time};
+
+/// Points 10 time units apart from t0, with `vals` in channel `ch` and
+/// 0 in the channels before it.
+pub(crate) fn points(t0: Time, ch: usize, vals: &[u16]) -> Vec<Point> {
+    vals.iter().enumerate()
+        .map(|(i, v)| {
+            let mut vs = vec![0; ch + 1];
+            vs[ch] = *v;
+            Point { t: t0 + i as Time * 10, vs }
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+//! Limits on channel values: threshold lines, shaded bands, and
+//! detecting when values cross thresholds.
+
+use crate::{Color, Point, Time, Value};
+
+/// Which side of a `Threshold` is beyond the limit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ThresholdDirection {
+    /// Values greater than the threshold are beyond it.
+    Above,
+
+    /// Values less than the threshold are beyond it.
+    Below,
+}
+
+/// A limit on one channel's values, drawn as a horizontal line.
+///
+/// Crossings are reported by `Graph::crossing_signal()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Threshold {
+    /// The channel the threshold applies to.
+    pub channel: usize,
+
+    /// The limit.
+    pub value: Value,
+
+    /// Which side of `value` is beyond the limit.
+    pub direction: ThresholdDirection,
+
+    /// The color of the line, and of highlighted points.
+    pub color: Color,
+
+    /// A label drawn next to the line, e.g. "warning". May be empty.
+    pub label: String,
+
+    /// Whether to draw points beyond the limit in `color` instead of the
+    /// channel's color. Where several thresholds highlight a point the
+    /// last one in the list wins, so list more severe thresholds last.
+    pub highlight: bool,
+}
+
+impl Threshold {
+    /// Returns true if `v` is beyond the limit.
+    pub fn is_beyond(&self, v: Value) -> bool {
+        match self.direction {
+            ThresholdDirection::Above => v > self.value,
+            ThresholdDirection::Below => v < self.value,
+        }
+    }
+}
+
+/// A range of one channel's values, drawn shaded behind the data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Band {
+    /// The channel the band applies to.
+    pub channel: usize,
+
+    /// The bottom of the band.
+    pub min: Value,
+
+    /// The top of the band.
+    pub max: Value,
+
+    /// The color to shade the band.
+    pub color: Color,
+}
+
+/// A channel's value crossing a `Threshold`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Crossing {
+    /// The index of the threshold in the list passed to
+    /// `Graph::set_thresholds()` or `ConfigBuilder::thresholds()`.
+    pub threshold: usize,
+
+    /// The channel the threshold applies to.
+    pub channel: usize,
+
+    /// The time of the first point on the new side of the threshold.
+    pub t: Time,
+
+    /// The value of that point.
+    pub value: Value,
+
+    /// True if the value went beyond the limit, false if it came back.
+    pub beyond: bool,
+}
+
+/// Returns the crossings in `points` of each of `thresholds`.
+///
+/// `beyond` holds whether the last value seen was beyond each
+/// threshold, and is updated. It starts out all false, so a first value
+/// beyond a threshold is reported as a crossing.
+pub(crate) fn detect_crossings(
+    thresholds: &[Threshold], beyond: &mut Vec<bool>, points: &[Point],
+) -> Vec<Crossing> {
+    beyond.resize(thresholds.len(), false);
+    let mut crossings = vec![];
+    for p in points {
+        for (i, th) in thresholds.iter().enumerate() {
+            let value = match p.vals().get(th.channel) {
+                Some(v) => *v,
+                None => continue,
+            };
+            let is_beyond = th.is_beyond(value);
+            if is_beyond != beyond[i] {
+                beyond[i] = is_beyond;
+                crossings.push(Crossing {
+                    threshold: i,
+                    channel: th.channel,
+                    t: p.t,
+                    value,
+                    beyond: is_beyond,
+                });
+            }
+        }
+    }
+    crossings
+}
+
+/// Returns the color of the last highlighting threshold on `channel`
+/// that `v` is beyond, if any.
+pub(crate) fn highlight_color(thresholds: &[Threshold], channel: usize, v: Value)
+                              -> Option<Color> {
+    thresholds.iter()
+        .rev()
+        .find(|th| th.highlight && th.channel == channel && th.is_beyond(v))
+        .map(|th| th.color)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect_crossings, highlight_color, Threshold, ThresholdDirection};
+    use crate::Color;
+    use crate::test_util::points;
+
+    fn threshold(value: u16, direction: ThresholdDirection, color: Color) -> Threshold {
+        Threshold {
+            channel: 1,
+            value,
+            direction,
+            color,
+            label: String::new(),
+            highlight: true,
+        }
+    }
+
+    #[test]
+    fn crossings() {
+        let ths = vec![threshold(100, ThresholdDirection::Above, Color(255, 255, 0)),
+                       threshold(20, ThresholdDirection::Below, Color(0, 0, 255))];
+        let mut beyond = vec![];
+        let cs = detect_crossings(&ths, &mut beyond, &points(0, 1, &[50, 150, 120, 10]));
+        let summary: Vec<_> = cs.iter().map(|c| (c.threshold, c.t, c.beyond)).collect();
+        assert_eq!(summary, vec![(0, 10, true), (0, 30, false), (1, 30, true)]);
+        assert_eq!(beyond, vec![false, true]);
+
+        // State carries over to the next batch of points.
+        let cs = detect_crossings(&ths, &mut beyond, &points(0, 1, &[5, 50]));
+        let summary: Vec<_> = cs.iter().map(|c| (c.threshold, c.t, c.beyond)).collect();
+        assert_eq!(summary, vec![(1, 10, false)]);
+    }
+
+    #[test]
+    fn highlights() {
+        let warn = Color(255, 255, 0);
+        let crit = Color(255, 0, 0);
+        let ths = vec![threshold(100, ThresholdDirection::Above, warn),
+                       threshold(200, ThresholdDirection::Above, crit)];
+        assert_eq!(highlight_color(&ths, 1, 50), None);
+        assert_eq!(highlight_color(&ths, 1, 150), Some(warn));
+        assert_eq!(highlight_color(&ths, 1, 250), Some(crit));
+        assert_eq!(highlight_color(&ths, 0, 250), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! Oscilloscope-style triggering, see `Graph::set_trigger()`.
+
+use crate::{Point, Time, Value};
+
+/// The edge of a signal that fires a `Trigger`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TriggerEdge {
+    /// The value rises through the trigger level.
+    Rising,
+
+    /// The value falls through the trigger level.
+    Falling,
+}
+
+/// When a triggered graph draws sweeps.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TriggerSweep {
+    /// Draw one sweep at the first trigger, then wait for
+    /// `Graph::rearm_trigger()`.
+    Single,
+
+    /// Draw a sweep at each trigger.
+    Normal,
+
+    /// Draw a sweep at each trigger, and if there is no trigger for a
+    /// whole window of time, draw the latest data anyway.
+    Auto,
+}
+
+/// A trigger condition for `ViewMode::Triggered`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trigger {
+    /// The channel to watch.
+    pub channel: usize,
+
+    /// The value the channel must pass through.
+    pub level: Value,
+
+    /// Which direction the channel must pass through `level`.
+    pub edge: TriggerEdge,
+
+    /// The minimum time from one trigger to the next.
+    pub holdoff: Time,
+
+    /// When to draw sweeps.
+    pub sweep: TriggerSweep,
+
+    /// The fraction of the graph's width to show before the trigger,
+    /// from 0.0 to 1.0.
+    pub pre_trigger: f64,
+}
+
+/// Finds triggers in data as it is ingested.
+pub(crate) struct TriggerState {
+    pub(crate) trigger: Trigger,
+    /// The previous value of the trigger channel.
+    prev: Option<Value>,
+    last_trigger_t: Option<Time>,
+    /// A trigger waiting for the data after it to arrive.
+    pending: Option<Time>,
+    /// The end time of the last sweep drawn, or the first time seen.
+    last_sweep_end: Option<Time>,
+    /// Set when a single sweep has been drawn.
+    done: bool,
+}
+
+impl TriggerState {
+    pub(crate) fn new(trigger: Trigger) -> TriggerState {
+        TriggerState {
+            trigger,
+            prev: None,
+            last_trigger_t: None,
+            pending: None,
+            last_sweep_end: None,
+            done: false,
+        }
+    }
+
+    /// Wait for another trigger after a single sweep.
+    pub(crate) fn rearm(&mut self) {
+        self.done = false;
+        self.pending = None;
+    }
+
+    /// Check newly ingested `points` for triggers, where `pre_dt` and
+    /// `post_dt` are the times to show before and after a trigger.
+    ///
+    /// Returns the end time of a sweep to draw, if one is ready.
+    pub(crate) fn update(&mut self, points: &[Point], pre_dt: Time, post_dt: Time)
+                         -> Option<Time> {
+        let latest_t = match points.last() {
+            Some(p) => p.t,
+            None => return None,
+        };
+        if self.last_sweep_end.is_none() {
+            self.last_sweep_end = Some(points[0].t);
+        }
+
+        let tr = &self.trigger;
+        for p in points {
+            let v = match p.vals().get(tr.channel) {
+                Some(v) => *v,
+                None => continue,
+            };
+            let edge = match (self.prev, tr.edge) {
+                (Some(prev), TriggerEdge::Rising) => prev < tr.level && v >= tr.level,
+                (Some(prev), TriggerEdge::Falling) => prev > tr.level && v <= tr.level,
+                (None, _) => false,
+            };
+            let held_off = matches!(self.last_trigger_t,
+                                    Some(last) if p.t < last.saturating_add(tr.holdoff));
+            if edge && !held_off && !self.done && self.pending.is_none() {
+                self.pending = Some(p.t);
+                self.last_trigger_t = Some(p.t);
+            }
+            self.prev = Some(v);
+        }
+
+        if let Some(trigger_t) = self.pending {
+            let end_t = trigger_t.saturating_add(post_dt);
+            if latest_t >= end_t {
+                self.pending = None;
+                self.last_sweep_end = Some(end_t);
+                if tr.sweep == TriggerSweep::Single {
+                    self.done = true;
+                }
+                return Some(end_t);
+            }
+            return None;
+        }
+
+        if tr.sweep == TriggerSweep::Auto &&
+            latest_t >= self.last_sweep_end.unwrap().saturating_add(pre_dt + post_dt) {
+            self.last_sweep_end = Some(latest_t);
+            return Some(latest_t);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Trigger, TriggerEdge, TriggerState, TriggerSweep};
+    use crate::test_util::points;
+
+    fn trigger(edge: TriggerEdge, sweep: TriggerSweep) -> Trigger {
+        Trigger {
+            channel: 0,
+            level: 100,
+            edge,
+            holdoff: 0,
+            sweep,
+            pre_trigger: 0.5,
+        }
+    }
+
+    #[test]
+    fn rising_edge_waits_for_post_trigger_data() {
+        let mut ts = TriggerState::new(trigger(TriggerEdge::Rising, TriggerSweep::Normal));
+        // Trigger at t=20, needs data up to t=50.
+        assert_eq!(ts.update(&points(0, 0, &[50, 90, 110, 120]), 30, 30), None);
+        assert_eq!(ts.update(&points(40, 0, &[130, 140]), 30, 30), Some(50));
+        // No new edge, no sweep.
+        assert_eq!(ts.update(&points(60, 0, &[150, 160, 170, 180]), 30, 30), None);
+    }
+
+    #[test]
+    fn falling_edge_and_holdoff() {
+        let mut tr = trigger(TriggerEdge::Falling, TriggerSweep::Normal);
+        tr.holdoff = 50;
+        let mut ts = TriggerState::new(tr);
+        // Falls at t=10, t=30 (held off), and t=70.
+        let ps = points(0, 0, &[150, 90, 150, 90, 150, 150, 150, 90, 150, 150, 150]);
+        assert_eq!(ts.update(&ps[..2], 0, 0), Some(10));
+        assert_eq!(ts.update(&ps[2..4], 0, 0), None);
+        assert_eq!(ts.update(&ps[4..], 0, 0), Some(70));
+    }
+
+    #[test]
+    fn single_and_auto() {
+        let mut ts = TriggerState::new(trigger(TriggerEdge::Rising, TriggerSweep::Single));
+        assert_eq!(ts.update(&points(0, 0, &[50, 150, 150]), 0, 10), Some(20));
+        assert_eq!(ts.update(&points(30, 0, &[50, 150, 150]), 0, 10), None);
+        ts.rearm();
+        assert_eq!(ts.update(&points(60, 0, &[50, 150, 150]), 0, 10), Some(80));
+
+        let mut ts = TriggerState::new(trigger(TriggerEdge::Rising, TriggerSweep::Auto));
+        assert_eq!(ts.update(&points(0, 0, &[50, 50, 50]), 10, 20), None);
+        assert_eq!(ts.update(&points(30, 0, &[50]), 10, 20), Some(30));
+    }
+}
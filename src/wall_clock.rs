@@ -0,0 +1,248 @@
+use crate::Time;
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Maps `Time` values to wall-clock times, and formats them for display.
+///
+/// Create an instance using a `WallClockBuilder`:
+///
+/// ```
+/// use rt_graph::{WallClock, WallClockBuilder};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let wall_clock: WallClock =
+///     WallClockBuilder::default()
+///         // Time values are microseconds since the Unix epoch.
+///         .epoch(UNIX_EPOCH)
+///         .time_unit(Duration::from_micros(1))
+///         // Display times in UTC+01:00.
+///         .utc_offset_secs(3600)
+///         .format("%H:%M:%S%.6f")
+///         .build()
+///         .unwrap();
+/// assert_eq!(wall_clock.format(1_500_000), "01:00:01.500000");
+/// ```
+#[derive(Builder, Clone, Debug)]
+#[builder(pattern = "owned")]
+pub struct WallClock {
+    /// The wall-clock time when `Time` is 0.
+    #[builder(default = "UNIX_EPOCH")]
+    epoch: SystemTime,
+
+    /// The duration of one unit of `Time`.
+    #[builder(default = "Duration::from_millis(1)")]
+    time_unit: Duration,
+
+    /// The timezone to display times in, as an offset from UTC in
+    /// seconds. Positive values are east of UTC.
+    #[builder(default = "0")]
+    utc_offset_secs: i32,
+
+    /// The format to display times in.
+    ///
+    /// Supported specifiers are `%Y` (year), `%m` (month), `%d` (day),
+    /// `%H` (hour), `%M` (minute), `%S` (second), `%.3f`, `%.6f` and
+    /// `%.9f` (fractional seconds including the leading '.'), `%z` (UTC
+    /// offset as `+hh:mm`) and `%%` (a literal '%').
+    #[builder(default = "\"%Y-%m-%d %H:%M:%S%.3f\".to_owned()", setter(into))]
+    format: String,
+}
+
+impl WallClock {
+    /// Return the wall-clock time for a `Time` value.
+    ///
+    /// Returns None if the result can't be represented as a `SystemTime`.
+    pub fn to_system_time(&self, t: Time) -> Option<SystemTime> {
+        let nanos = self.time_unit.as_nanos().checked_mul(t as u128)?;
+        let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+        let d = Duration::new(secs, (nanos % 1_000_000_000) as u32);
+        self.epoch.checked_add(d)
+    }
+
+    /// Return the `Time` value for a wall-clock time, rounded down to a
+    /// whole unit of `Time`.
+    ///
+    /// Returns None if `st` is before the epoch or the result does not
+    /// fit in a `Time`.
+    pub fn from_system_time(&self, st: SystemTime) -> Option<Time> {
+        let d = st.duration_since(self.epoch).ok()?;
+        let unit_nanos = self.time_unit.as_nanos();
+        if unit_nanos == 0 {
+            return None;
+        }
+        let t = d.as_nanos() / unit_nanos;
+        if t > Time::MAX as u128 { None } else { Some(t as Time) }
+    }
+
+    /// Return the duration of one unit of `Time`.
+    pub fn time_unit(&self) -> Duration {
+        self.time_unit
+    }
+
+    /// Return the timezone offset from UTC in seconds.
+    pub fn utc_offset_secs(&self) -> i32 {
+        self.utc_offset_secs
+    }
+
+    /// Format a `Time` value as wall-clock time, using the configured
+    /// timezone and format.
+    pub fn format(&self, t: Time) -> String {
+        self.format_with(t, &self.format)
+    }
+
+    /// Format a `Time` value as wall-clock time, using the configured
+    /// timezone and the format string `fmt`. See `WallClockBuilder::format`
+    /// for the supported specifiers.
+    ///
+    /// Falls back to the raw `Time` value if it is out of range.
+    pub fn format_with(&self, t: Time, fmt: &str) -> String {
+        match self.to_system_time(t) {
+            Some(st) => format_system_time(st, self.utc_offset_secs, fmt),
+            None => t.to_string(),
+        }
+    }
+}
+
+/// Format `st` in the timezone `utc_offset_secs` east of UTC.
+fn format_system_time(st: SystemTime, utc_offset_secs: i32, fmt: &str) -> String {
+    let (secs, nanos) = match st.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            // Before the Unix epoch.
+            let d = e.duration();
+            if d.subsec_nanos() == 0 {
+                (-(d.as_secs() as i64), 0)
+            } else {
+                (-(d.as_secs() as i64) - 1, 1_000_000_000 - d.subsec_nanos())
+            }
+        }
+    };
+    let local_secs = secs + utc_offset_secs as i64;
+    let days = local_secs.div_euclid(86400);
+    let secs_of_day = local_secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+
+    let mut out = String::with_capacity(fmt.len() + 16);
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", year)),
+            Some('m') => out.push_str(&format!("{:02}", month)),
+            Some('d') => out.push_str(&format!("{:02}", day)),
+            Some('H') => out.push_str(&format!("{:02}", secs_of_day / 3600)),
+            Some('M') => out.push_str(&format!("{:02}", (secs_of_day / 60) % 60)),
+            Some('S') => out.push_str(&format!("{:02}", secs_of_day % 60)),
+            Some('z') => {
+                let sign = if utc_offset_secs < 0 { '-' } else { '+' };
+                let abs = utc_offset_secs.abs();
+                out.push_str(&format!("{}{:02}:{:02}", sign, abs / 3600, (abs / 60) % 60));
+            },
+            Some('%') => out.push('%'),
+            Some('.') => {
+                // Fractional seconds: "%.3f", "%.6f", or "%.9f".
+                let digits = chars.next();
+                let f = chars.next();
+                match (digits, f) {
+                    (Some(d @ '3'), Some('f')) |
+                    (Some(d @ '6'), Some('f')) |
+                    (Some(d @ '9'), Some('f')) => {
+                        let digits = d.to_digit(10).unwrap() as usize;
+                        let frac = nanos / 10u32.pow(9 - digits as u32);
+                        out.push_str(&format!(".{:0width$}", frac, width = digits));
+                    },
+                    _ => {
+                        // Unrecognised, output it verbatim.
+                        out.push_str("%.");
+                        out.extend(digits);
+                        out.extend(f);
+                    }
+                }
+            },
+            Some(other) => {
+                // Unrecognised, output it verbatim.
+                out.push('%');
+                out.push(other);
+            },
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+/// Convert a count of days since 1970-01-01 to a (year, month, day)
+/// date in the proleptic Gregorian calendar.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097); // [0, 146096]
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365; // [0, 399]
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+    let mp = (5 * doy + 2) / 153; // [0, 11]
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32; // [1, 31]
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32; // [1, 12]
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{civil_from_days, WallClockBuilder};
+    use crate::Time;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+    }
+
+    #[test]
+    fn to_and_from_system_time() {
+        let wc = WallClockBuilder::default()
+            .epoch(UNIX_EPOCH + Duration::from_secs(100))
+            .time_unit(Duration::from_micros(1))
+            .build()
+            .unwrap();
+        let st = wc.to_system_time(2_500_000).unwrap();
+        assert_eq!(st, UNIX_EPOCH + Duration::from_millis(102_500));
+        assert_eq!(wc.from_system_time(st), Some(2_500_000));
+        assert_eq!(wc.from_system_time(UNIX_EPOCH), None);
+
+        let coarse = WallClockBuilder::default()
+            .time_unit(Duration::from_secs(u64::MAX / 2))
+            .build()
+            .unwrap();
+        assert_eq!(coarse.to_system_time(Time::MAX), None);
+        assert_eq!(coarse.format(Time::MAX), Time::MAX.to_string());
+    }
+
+    #[test]
+    fn format() {
+        let wc = WallClockBuilder::default()
+            .epoch(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+            .build()
+            .unwrap();
+        // Default format in UTC, `Time` in milliseconds.
+        assert_eq!(wc.format(1234), "2020-09-13 12:26:41.234");
+
+        let wc = WallClockBuilder::default()
+            .epoch(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+            .utc_offset_secs(-(5 * 3600 + 30 * 60))
+            .format("%d/%m/%Y %H:%M:%S %z %% %q")
+            .build()
+            .unwrap();
+        assert_eq!(wc.format(0), "13/09/2020 06:56:40 -05:30 % %q");
+        assert_eq!(wc.format_with(1, "%S%.6f"), "40.001000");
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    axis::{draw_bottom_tick, draw_left_tick, value_ticks},
+    Color, Error, Graph, Result, Time, Value,
+};
+use gtk::prelude::*;
+use std::{
+    cell::Cell,
+    rc::Rc,
+};
+
+/// Width of the Y axis to the left of the plot in pixels.
+const Y_AXIS_WIDTH: f64 = 50.0;
+/// Height of the X axis under the plot in pixels.
+const X_AXIS_HEIGHT: f64 = 20.0;
+/// Minimum distance between ticks on either axis in pixels.
+const AXIS_MIN_TICK_SPACING: f64 = 50.0;
+/// Segments of the trail are drawn in this many steps of opacity, so
+/// each step can be stroked at once.
+const FADE_STEPS: usize = 32;
+
+/// The configuration of an `XyPlot`.
+///
+/// Create an instance using an `XyPlotConfigBuilder`.
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct XyPlotConfig {
+    /// The channel plotted on the X axis. This can be changed later
+    /// with `XyPlot::set_channels()`.
+    #[builder(default = "0")]
+    x_channel: usize,
+
+    /// The channel plotted on the Y axis. This can be changed later
+    /// with `XyPlot::set_channels()`.
+    #[builder(default = "1")]
+    y_channel: usize,
+
+    /// How far back in time to draw points, from the latest point. This
+    /// can be changed later with `XyPlot::set_trail()`.
+    #[builder(default = "10_000")]
+    trail: Time,
+
+    /// Whether older points fade out along the trail.
+    #[builder(default = "true")]
+    fade: bool,
+
+    /// The value at the left of the plot.
+    #[builder(default = "0")]
+    x_min: Value,
+
+    /// The value at the right of the plot.
+    #[builder(default = "Value::MAX")]
+    x_max: Value,
+
+    /// The value at the bottom of the plot.
+    #[builder(default = "0")]
+    y_min: Value,
+
+    /// The value at the top of the plot.
+    #[builder(default = "Value::MAX")]
+    y_max: Value,
+
+    /// Initial width in pixels.
+    #[builder(default = "300")]
+    width: u32,
+
+    /// Initial height in pixels.
+    #[builder(default = "300")]
+    height: u32,
+
+    /// The color of the trail.
+    #[builder(default = "Color(0, 255, 0)")]
+    color: Color,
+}
+
+/// A GTK widget that plots one channel of a `Graph`'s data against
+/// another, e.g. for a phase portrait or a Lissajous figure.
+///
+/// Consecutive points are joined into a trail covering the latest
+/// `trail` of time, redrawn each frame from the graph's data.
+pub struct XyPlot {
+    s: Rc<State>,
+}
+
+struct State {
+    config: XyPlotConfig,
+    graph: Graph,
+    drawing_area: gtk::DrawingArea,
+
+    x_channel: Cell<usize>,
+    y_channel: Cell<usize>,
+    trail: Cell<Time>,
+}
+
+impl XyPlot {
+    /// Build and show an `XyPlot` widget for `graph`'s data in the
+    /// target `gtk::Container`.
+    ///
+    /// Returns an error if either configured channel is not a valid
+    /// channel index.
+    pub fn build_ui<C>(config: XyPlotConfig, graph: &Graph, container: &C) -> Result<XyPlot>
+        where C: IsA<gtk::Container> + IsA<gtk::Widget>
+    {
+        check_channels(graph, config.x_channel, config.y_channel)?;
+
+        let drawing_area = gtk::DrawingAreaBuilder::new()
+            .height_request(config.height as i32)
+            .width_request(config.width as i32)
+            .build();
+        container.add(&drawing_area);
+
+        let s = Rc::new(State {
+            x_channel: Cell::new(config.x_channel),
+            y_channel: Cell::new(config.y_channel),
+            trail: Cell::new(config.trail),
+
+            graph: graph.clone(),
+            drawing_area: drawing_area.clone(),
+            config,
+        });
+
+        let sc = s.clone();
+        drawing_area.connect_draw(move |_ctrl, ctx| {
+            xy_plot_draw(ctx, &sc)
+        });
+
+        // Redraw with the latest data every frame.
+        drawing_area.add_tick_callback(move |ctrl, _clock| {
+            ctrl.queue_draw();
+            Continue(true)
+        });
+
+        drawing_area.show_all();
+
+        Ok(XyPlot {
+            s,
+        })
+    }
+
+    /// Plot channel `x` on the X axis against channel `y` on the Y axis.
+    ///
+    /// Returns an error if either is not a valid channel index.
+    pub fn set_channels(&self, x: usize, y: usize) -> Result<()> {
+        check_channels(&self.s.graph, x, y)?;
+        self.s.x_channel.set(x);
+        self.s.y_channel.set(y);
+        self.s.drawing_area.queue_draw();
+        Ok(())
+    }
+
+    /// Return the channels plotted on the X and Y axes.
+    pub fn channels(&self) -> (usize, usize) {
+        (self.s.x_channel.get(), self.s.y_channel.get())
+    }
+
+    /// Change how far back in time to draw points.
+    pub fn set_trail(&self, trail: Time) {
+        self.s.trail.set(trail);
+        self.s.drawing_area.queue_draw();
+    }
+
+    /// Return how far back in time points are drawn.
+    pub fn trail(&self) -> Time {
+        self.s.trail.get()
+    }
+
+    /// Return the `gtk::DrawingArea` the plot is drawn on.
+    pub fn drawing_area(&self) -> gtk::DrawingArea {
+        self.s.drawing_area.clone()
+    }
+}
+
+/// Returns an error if x or y is not a valid channel index of `graph`.
+fn check_channels(graph: &Graph, x: usize, y: usize) -> Result<()> {
+    let num_channels = graph.channel_names().len();
+    match [x, y].iter().find(|ch| **ch >= num_channels) {
+        Some(ch) => Err(Error::String(format!("Invalid channel index {}", ch))),
+        None => Ok(()),
+    }
+}
+
+/// Returns the position of `v` from 0.0 at `v0` to 1.0 at `v1`.
+fn fraction(v: Value, v0: Value, v1: Value) -> f64 {
+    (v as f64 - v0 as f64) / (v1 as f64 - v0 as f64).max(1.0)
+}
+
+/// Handle the plot's draw signal.
+fn xy_plot_draw(ctx: &cairo::Context, s: &State) -> Inhibit {
+    let c = &s.config;
+    let w = s.drawing_area.get_allocated_width() as f64;
+    let h = s.drawing_area.get_allocated_height() as f64;
+    let (plot_x, plot_w) = (Y_AXIS_WIDTH, (w - Y_AXIS_WIDTH).max(1.0));
+    let plot_h = (h - X_AXIS_HEIGHT).max(1.0);
+    let to_x = |v: Value| plot_x + fraction(v, c.x_min, c.x_max) * plot_w;
+    let to_y = |v: Value| (1.0 - fraction(v, c.y_min, c.y_max)) * plot_h;
+
+    s.graph.fill_background(ctx, plot_x, 0.0, plot_w, plot_h);
+
+    // Axes
+    s.graph.set_axis_style(ctx);
+    ctx.set_line_width(1.0);
+    let y_step = AXIS_MIN_TICK_SPACING * (c.y_max as f64 - c.y_min as f64) / plot_h;
+    for v in value_ticks(c.y_min, c.y_max, y_step) {
+        draw_left_tick(ctx, plot_x, to_y(v).round() + 0.5, &v.to_string(), 0.0, plot_h);
+    }
+    let x_step = AXIS_MIN_TICK_SPACING * (c.x_max as f64 - c.x_min as f64) / plot_w;
+    for v in value_ticks(c.x_min, c.x_max, x_step) {
+        draw_bottom_tick(ctx, to_x(v).round() + 0.5, plot_h, &v.to_string(), h - 4.0, w);
+    }
+
+    // Trail
+    let (x_ch, y_ch) = (s.x_channel.get(), s.y_channel.get());
+    let trail = s.trail.get();
+    let t1 = s.graph.last_t();
+    let points = s.graph.points_in(t1.saturating_sub(trail), t1.saturating_add(1));
+    if points.len() < 2 {
+        return Inhibit(false);
+    }
+
+    ctx.save();
+    ctx.rectangle(plot_x, 0.0, plot_w, plot_h);
+    ctx.clip();
+    ctx.set_line_width(1.0);
+    // Group segments by opacity, oldest first, so newer segments are
+    // drawn on top.
+    let mut steps: Vec<Vec<(f64, f64, f64, f64)>> = vec![vec![]; FADE_STEPS];
+    for pair in points.windows(2) {
+        let (p0, p1) = (&pair[0], &pair[1]);
+        let step = if c.fade && trail > 0 {
+            let age = (t1 - p1.t) as f64 / trail as f64;
+            (((1.0 - age) * FADE_STEPS as f64) as usize).min(FADE_STEPS - 1)
+        } else {
+            FADE_STEPS - 1
+        };
+        steps[step].push((to_x(p0.vals()[x_ch]), to_y(p0.vals()[y_ch]),
+                          to_x(p1.vals()[x_ch]), to_y(p1.vals()[y_ch])));
+    }
+    for (i, segments) in steps.iter().enumerate() {
+        if segments.is_empty() {
+            continue;
+        }
+        ctx.set_source_rgba(c.color.r() as f64 / 255.0,
+                            c.color.g() as f64 / 255.0,
+                            c.color.b() as f64 / 255.0,
+                            (i + 1) as f64 / FADE_STEPS as f64);
+        for (x0, y0, x1, y1) in segments.iter() {
+            ctx.move_to(*x0, *y0);
+            ctx.line_to(*x1, *y1);
+        }
+        ctx.stroke();
+    }
+    ctx.restore();
+
+    Inhibit(false)
+}